@@ -3,6 +3,7 @@
   package,
   identity,
   extraRecipients,
+  recipientsFile,
   extraPackages,
   pinentryPackage,
   ...
@@ -12,7 +13,9 @@ let
   inherit (pkgs.lib) concatStringsSep makeBinPath optionalString getExe;
 
   bin = getExe package;
-  recipientsArg = concatStringsSep " " (map (n: "--recipient ${n}") extraRecipients);
+  recipientsArg =
+    concatStringsSep " " (map (n: "--recipient ${n}") extraRecipients)
+    + optionalString (recipientsFile != null) " --recipients-file ${recipientsFile}";
   pathPrefix = makeBinPath extraPackages;
 
 in
@@ -0,0 +1,50 @@
+{
+  nodes,
+  lib,
+  pkgs,
+  package,
+  identity,
+  extraPackages,
+  pinentryPackage,
+  ...
+}:
+let
+  inherit (pkgs) writeShellScriptBin;
+  inherit (lib)
+    concatStringsSep
+    attrValues
+    makeBinPath
+    filter
+    optionalString
+    getExe
+    ;
+  bin = getExe package;
+
+  profilesArgs = concatStringsSep " " (
+    map (
+      v:
+      "--profile"
+      + " "
+      + (pkgs.writeTextFile {
+        name = "vaultix-material";
+        text = builtins.toJSON {
+          inherit (v.config.vaultix)
+            beforeUserborn
+            placeholder
+            secrets
+            settings
+            templates
+            ;
+        };
+      })
+    ) (filter (v: v.config ? vaultix) (attrValues nodes))
+  );
+
+  pathPrefix = makeBinPath extraPackages;
+
+in
+writeShellScriptBin "rekey" ''
+  export PATH=${pathPrefix}:$PATH
+  ${optionalString (pinentryPackage != null) "export PINENTRY_PROGRAM=${getExe pinentryPackage}"}
+  ${bin} ${profilesArgs} rekey --identity ${identity} "$@"
+''
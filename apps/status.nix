@@ -0,0 +1,42 @@
+{
+  nodes,
+  lib,
+  pkgs,
+  package,
+  cache,
+  ...
+}:
+let
+  inherit (pkgs) writeShellScriptBin;
+  inherit (lib)
+    concatStringsSep
+    attrValues
+    filter
+    getExe
+    ;
+  bin = getExe package;
+
+  profilesArgs = concatStringsSep " " (
+    map (
+      v:
+      "--profile"
+      + " "
+      + (pkgs.writeTextFile {
+        name = "vaultix-material";
+        text = builtins.toJSON {
+          inherit (v.config.vaultix)
+            beforeUserborn
+            placeholder
+            secrets
+            settings
+            templates
+            ;
+        };
+      })
+    ) (filter (v: v.config ? vaultix) (attrValues nodes))
+  );
+
+in
+writeShellScriptBin "status" ''
+  ${bin} ${profilesArgs} status --cache ${cache} "$@"
+''
@@ -14,6 +14,7 @@
       defaultSecretDirectory ? "./secrets",
       identity,
       extraRecipients ? [ ],
+      recipientsFile ? null,
       extraPackages ? [ ],
       pinentryPackage ? null,
       systems ? [
@@ -35,6 +36,8 @@
           [
             "renc"
             "edit"
+            "rekey"
+            "status"
           ]
           (
             app:
@@ -43,6 +46,7 @@
                 nodes
                 identity
                 extraRecipients
+                recipientsFile
                 extraPackages
                 pinentryPackage
                 cache
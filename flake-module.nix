@@ -87,6 +87,21 @@ in
                 to decrypt all secrets.
               '';
             };
+            recipientsFile = mkOption {
+              type =
+                types.nullOr (
+                  types.addCheck types.str (s: (builtins.substring 0 1 s) == ".") // {
+                    description = "path string relative to flake root";
+                  }
+                );
+              default = null;
+              example = "./secrets/recipients.txt";
+              description = ''
+                `path str` that relative to flake root, of an age recipients file
+                (one recipient per line, `#` for comments) which edit encrypts to
+                in addition to extraRecipients.
+              '';
+            };
             extraPackages = mkOption {
               type = with types; listOf package;
               default = [ ];
@@ -112,6 +127,8 @@ in
                   [
                     "renc"
                     "edit"
+                    "rekey"
+                    "status"
                   ]
                   (
                     app:
@@ -120,6 +137,7 @@ in
                         nodes
                         identity
                         extraRecipients
+                        recipientsFile
                         cache
                         extraPackages
                         pinentryPackage
@@ -97,6 +97,41 @@ let
         '';
       };
 
+      keepGenerations = mkOption {
+        type = types.ints.unsigned;
+        default = 1;
+        description = ''
+          Number of previous generations kept under
+          {option}`vaultix.settings.decryptedMountPoint` for rollback.
+          Older ones and files they deployed to custom `path` are removed
+          after each deploy.
+        '';
+      };
+
+      mountStrategy = mkOption {
+        type = types.enum [
+          "ramfs"
+          "tmpfs"
+          "existing"
+        ];
+        default = "ramfs";
+        description = ''
+          How {option}`vaultix.settings.decryptedMountPoint` is made memory
+          backed when it not exists. `tmpfs` mounts with `noswap` if kernel
+          supports, `existing` creates no mount and requires the directory
+          already on ramfs or tmpfs, for containers that can't mount.
+        '';
+      };
+
+      tmpfsSize = mkOption {
+        type = types.nullOr types.str;
+        default = null;
+        example = "16M";
+        description = ''
+          `size=` of the tmpfs mounted by `tmpfs` mount strategy.
+        '';
+      };
+
       hostKeys = mkOption {
         type = lib.types.listOf lib.types.attrs;
         default = config.services.openssh.hostKeys;
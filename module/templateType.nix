@@ -79,6 +79,49 @@ in
           Group of the built template.
         '';
       };
+      restartUnits = mkOption {
+        type = types.listOf types.str;
+        default = [ ];
+        example = [ "postgresql.service" ];
+        description = ''
+          Systemd units to restart once the content of this built template changed
+          while deploying.
+        '';
+      };
+      reloadUnits = mkOption {
+        type = types.listOf types.str;
+        default = [ ];
+        example = [ "nginx.service" ];
+        description = ''
+          Systemd units to reload once the content of this built template changed
+          while deploying. Units also in `restartUnits` are only restarted.
+        '';
+      };
+      parentDir = {
+        create = mkEnableOption "creating missing parent directories of `path`";
+        mode = mkOption {
+          type = types.str;
+          default = "0755";
+          description = ''
+            Permissions mode of the created parent directories.
+          '';
+        };
+        owner = mkOption {
+          type = types.str;
+          default = "root";
+          description = ''
+            User of the created parent directories.
+          '';
+        };
+        group = mkOption {
+          type = types.str;
+          default = "root";
+          description = ''
+            Group of the created parent directories.
+          '';
+        };
+        allowDisk = mkEnableOption "creating parent directories on filesystems other than ramfs or tmpfs";
+      };
     };
   });
 }
@@ -1,7 +1,10 @@
 use eyre::{Context, ContextCompat, Result, eyre};
 use log::debug;
 
-use crate::util::secmap::{RencBuilder, RencCtx};
+use crate::util::{
+    report,
+    secmap::{RencBuilder, RencCtx},
+};
 
 use super::renc::CompleteProfile;
 
@@ -19,6 +22,7 @@ impl CompleteProfile<'_> {
             .renced_stored(&ctx, profile.settings.cache_in_store.clone().into())
             .inner();
 
+        report::processed(inst.len());
         inst.values().try_for_each(|p| {
             debug!("checking in-store path: {}", p.path.display());
             if !p.path.exists() {
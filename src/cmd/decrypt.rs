@@ -0,0 +1,79 @@
+use std::{
+    fs,
+    io::{self, IsTerminal, Write},
+    path::Path,
+};
+
+use eyre::{Context, bail, eyre};
+use log::info;
+
+use crate::{
+    parser::identity::{ParsedIdentity, RawIdentity},
+    util::{
+        report,
+        secbuf::{AgeEnc, Decryptable, SecBuf},
+    },
+};
+
+use super::DecryptSubCmd;
+
+/// content unsafe to dump to a terminal: not utf-8 or carrying control chars
+fn is_binary(content: &[u8]) -> bool {
+    std::str::from_utf8(content).map_or(true, |s| {
+        s.chars()
+            .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+    })
+}
+
+pub fn decrypt(arg: &DecryptSubCmd) -> eyre::Result<()> {
+    let DecryptSubCmd {
+        file,
+        identity,
+        output,
+        force,
+    } = arg;
+
+    if output.is_none() && report::is_json() {
+        bail!("stdout is occupied by `--output json`, use -o");
+    }
+
+    let ParsedIdentity { identity, .. } = RawIdentity::from(identity.clone()).try_into()?;
+
+    let plain = fs::read(file)
+        .map(SecBuf::<AgeEnc>::new)
+        .wrap_err_with(|| eyre!("read encrypted file {file} error"))?
+        .decrypt(identity.as_ref())
+        .wrap_err_with(|| eyre!("decrypt {file} fail"))?;
+
+    match output {
+        Some(o) => {
+            plain
+                .write_atomic(Path::new(o), 0o600)
+                .wrap_err_with(|| eyre!("write decrypted file {o} error"))?;
+            info!("decrypted file written to {o}");
+            report::written(Path::new(o));
+        }
+        None => {
+            let mut stdout = io::stdout().lock();
+            if !force && stdout.is_terminal() && is_binary(plain.buf_ref()) {
+                bail!("refuse to write binary content to terminal, use -o, redirect or --force");
+            }
+            stdout.write_all(plain.buf_ref())?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_detect() {
+        assert!(!is_binary(b"hunter2\n"));
+        assert!(!is_binary("a=1\r\n\tb=2".as_bytes()));
+        assert!(is_binary(b"\x1b[2J"));
+        assert!(is_binary(&[0xff, 0xfe, 0x00]));
+    }
+}
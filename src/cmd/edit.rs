@@ -2,17 +2,25 @@ use std::{
     fs::{self, OpenOptions},
     io::{self, Write},
     iter,
-    path::PathBuf,
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
 };
 
 use crate::util::{
+    memfs::is_memory_backed,
+    report,
     secbuf::{AgeEnc, Plain, SecBuf},
     secmap::{GetSec, SecPath},
+    shred::shred_file,
 };
 use crate::{
     parser::{
+        header::Header,
         identity::{ParsedIdentity, RawIdentity},
-        recipient::RecipString,
+        recipient::{
+            RecipString, build_recipients, gather_recipients, read_sidecar, sidecar_of,
+            write_sidecar,
+        },
     },
     util::secmap::InRepo,
 };
@@ -20,16 +28,99 @@ use crate::{
 use crate::util::secbuf::Decryptable;
 use age::Recipient;
 use eyre::{Context, ContextCompat, bail, eyre};
-use log::info;
+use log::{debug, error, info, warn};
 use nom::AsBytes;
 
 use super::EditSubCmd;
 
+/// plaintext file handed to editor, overwritten and removed on drop
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn create(dir: &Path, secret_file: &str, content: &[u8]) -> eyre::Result<Self> {
+        let name = Path::new(secret_file)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let path = dir.join(format!("vaultix-{}-{name}", std::process::id()));
+
+        let mut f = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+            .wrap_err_with(|| eyre!("create scratch file {} error", path.display()))?;
+        let ret = Self(path);
+        f.write_all(content)?;
+        Ok(ret)
+    }
+
+    fn edit(&self) -> eyre::Result<SecBuf<Plain>> {
+        edit::edit_file(&self.0)?;
+        fs::read(&self.0)
+            .map(SecBuf::new)
+            .wrap_err_with(|| eyre!("read edited content error"))
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        if let Err(e) = shred_file(&self.0) {
+            error!("{e}");
+        }
+    }
+}
+
+/// first memory backed one of `$XDG_RUNTIME_DIR`, `/dev/shm` and `$TMPDIR`
+fn scratch_dir(allow_disk_tmp: bool) -> eyre::Result<PathBuf> {
+    let tmp_dir = std::env::temp_dir();
+    let candidates = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .into_iter()
+        .chain([PathBuf::from("/dev/shm"), tmp_dir.clone()]);
+
+    for dir in candidates {
+        if dir.is_dir() && is_memory_backed(&dir).unwrap_or(false) {
+            debug!("using {} for editing", dir.display());
+            return Ok(dir);
+        }
+    }
+
+    if allow_disk_tmp {
+        warn!(
+            "no memory backed directory found, plaintext will be written to {}",
+            tmp_dir.display()
+        );
+        return Ok(tmp_dir);
+    }
+
+    bail!(
+        "no memory backed directory found among $XDG_RUNTIME_DIR, /dev/shm and {}. pass --allow-disk-tmp to edit in it anyway",
+        tmp_dir.display()
+    )
+}
+
+/// `first` followed by ones of `then` not in it, without duplicates
+fn merged<'a>(
+    first: impl Iterator<Item = &'a RecipString>,
+    then: &'a [RecipString],
+) -> Vec<RecipString> {
+    first.chain(then).fold(vec![], |mut acc, r| {
+        if !acc.contains(r) {
+            acc.push(r.clone());
+        }
+        acc
+    })
+}
+
 pub fn edit(arg: EditSubCmd) -> eyre::Result<()> {
     let EditSubCmd {
         file,
         identity,
         recipient,
+        recipients_file,
+        reset_recipients,
+        allow_disk_tmp,
     } = arg;
 
     match std::fs::exists(&file) {
@@ -44,60 +135,122 @@ pub fn edit(arg: EditSubCmd) -> eyre::Result<()> {
         .with_context(|| eyre!("must provide identity to decrypt content"))
         .and_then(|i| RawIdentity::from(i).try_into())?;
 
-    let recips: Vec<Box<dyn Recipient + Send>> = recipient
+    // only `-r` ones are recorded, recipients files are read again on every edit
+    let recorded = read_sidecar(Path::new(&file))?;
+    let extras = gather_recipients(&recipient, &[])?;
+    let from_files = gather_recipients(&[], &recipients_file)?;
+    let given = |r: &RecipString| extras.contains(r) || from_files.contains(r);
+
+    let to_record: Vec<RecipString> = if reset_recipients {
+        recorded
+            .iter()
+            .filter(|r| !given(r))
+            .for_each(|r| warn!("recipient {} removed from {file}", r.as_str()));
+        extras.clone()
+    } else {
+        recorded.iter().filter(|r| !given(r)).for_each(|r| {
+            warn!(
+                "recipient {} recorded in {} but not given now, still encrypting to it. \
+                    remove its line or pass --reset-recipients to revoke",
+                r.as_str(),
+                sidecar_of(Path::new(&file)).display()
+            )
+        });
+        merged(recorded.iter().filter(|r| !from_files.contains(r)), &extras)
+    };
+    let wanted = merged(to_record.iter(), &from_files);
+
+    let recips: Vec<Box<dyn Recipient + Send>> = build_recipients(wanted.iter().cloned())?
         .into_iter()
-        .map(|s| {
-            TryInto::<Box<dyn Recipient + Send>>::try_into(RecipString::from(s)).expect("convert")
-        })
-        .chain::<std::iter::Once<Box<dyn Recipient + Send>>>(iter::once(id_parsed.recipient))
+        .chain(iter::once(id_parsed.recipient))
         .collect();
 
-    let encrypt_content = |v: Vec<u8>| -> eyre::Result<Vec<u8>> {
-        Ok(SecBuf::<Plain>::new(v)
-            .encrypt(recips.iter().map(|i| i.as_ref()))?
-            .inner())
+    let record_recipients = || -> eyre::Result<()> {
+        if to_record != recorded {
+            write_sidecar(Path::new(&file), &to_record)?;
+            let sidecar = sidecar_of(Path::new(&file));
+            if to_record.is_empty() {
+                info!("no extra recipient left, removed {}", sidecar.display());
+            } else {
+                info!(
+                    "{} recipient(s) recorded in {}",
+                    to_record.len(),
+                    sidecar.display()
+                );
+            }
+        }
+        Ok(())
+    };
+
+    let encrypt_content = |v: SecBuf<Plain>| -> eyre::Result<Vec<u8>> {
+        Ok(v.encrypt(recips.iter().map(|i| i.as_ref()))?.inner())
     };
 
+    let scratch_dir = scratch_dir(allow_disk_tmp)?;
+
     if PathBuf::from(&file).exists() {
-        let buf = SecPath::<String, InRepo>::new(file.clone())
+        let encrypted = SecPath::<String, InRepo>::new(file.clone())
             .read_buffer()
-            .map(SecBuf::<AgeEnc>::from)?
-            .decrypt(id_parsed.identity.as_ref())?
-            .inner();
-        let pre_hash = blake3::hash(buf.as_slice());
+            .map(SecBuf::<AgeEnc>::from)?;
+
+        let recipients_differ = match Header::parse(encrypted.buf_ref()) {
+            Ok(h) => {
+                let before = h.recipient_stanzas().count();
+                if before > recips.len() {
+                    warn!(
+                        "{file} was encrypted to {before} recipient(s), will be encrypted to {} after edit",
+                        recips.len()
+                    )
+                }
+                before != recips.len()
+            }
+            Err(e) => {
+                debug!("{e}, skip checking recipients");
+                false
+            }
+        };
+
+        let buf = encrypted.decrypt(id_parsed.identity.as_ref())?;
+        let pre_hash = blake3::hash(buf.buf_ref());
 
         let edited_buf_encrypted = {
-            let edited = edit::edit(buf)?;
+            let edited = Scratch::create(&scratch_dir, &file, buf.buf_ref())?.edit()?;
+            drop(buf);
 
-            if blake3::hash(edited.as_bytes()) == pre_hash {
-                info!("file unchange");
-                return Ok(());
+            if blake3::hash(edited.buf_ref()) == pre_hash {
+                if to_record == recorded && !recipients_differ {
+                    info!("file unchange");
+                    return Ok(());
+                }
+                info!("file unchange, re-encrypting for changed recipients");
             }
 
-            encrypt_content(edited.into_bytes())?
+            encrypt_content(edited)?
         };
-        let mut file = OpenOptions::new().write(true).truncate(true).open(&file)?;
+        let mut target_file = OpenOptions::new().write(true).truncate(true).open(&file)?;
 
-        file.write_all(edited_buf_encrypted.as_bytes())?;
-        return Ok(());
+        target_file.write_all(edited_buf_encrypted.as_bytes())?;
+        report::written(Path::new(&file));
+        return record_recipients();
     }
 
     let edited_buf_encrypted = {
-        let edited = edit::edit(vec![])?;
-        encrypt_content(edited.into_bytes())?
+        let edited = Scratch::create(&scratch_dir, &file, &[])?.edit()?;
+        encrypt_content(edited)?
     };
 
     let mut target_file = fs::OpenOptions::new()
         .write(true)
         .create(true)
         .truncate(true)
-        .open(file)?;
+        .open(&file)?;
 
     target_file
         .write_all(&edited_buf_encrypted)
         .wrap_err_with(|| eyre!("write renc file error"))?;
 
     info!("edited file written");
+    report::written(Path::new(&file));
 
-    Ok(())
+    record_recipients()
 }
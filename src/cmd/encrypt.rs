@@ -0,0 +1,85 @@
+use std::{
+    fs,
+    io::{self, IsTerminal, Read, Write},
+    path::Path,
+};
+
+use eyre::{Context, bail, eyre};
+use log::{debug, info};
+
+use crate::{
+    parser::{
+        identity::{ParsedIdentity, RawIdentity},
+        recipient::collect_recipients,
+    },
+    util::{
+        report,
+        secbuf::{Plain, SecBuf},
+    },
+};
+
+use super::EncryptSubCmd;
+
+/// read whole plaintext from file, or stdin if `None` or `-`
+fn read_plain(input: Option<&str>) -> eyre::Result<SecBuf<Plain>> {
+    match input {
+        None | Some("-") => {
+            debug!("reading plaintext from stdin");
+            let mut buf = Vec::new();
+            io::stdin()
+                .lock()
+                .read_to_end(&mut buf)
+                .wrap_err_with(|| eyre!("read stdin error"))?;
+            Ok(SecBuf::new(buf))
+        }
+        Some(f) => fs::read(f)
+            .map(SecBuf::new)
+            .wrap_err_with(|| eyre!("read plaintext {f} error")),
+    }
+}
+
+pub fn encrypt(arg: &EncryptSubCmd) -> eyre::Result<()> {
+    let EncryptSubCmd {
+        input,
+        identity,
+        recipient,
+        recipients_file,
+        output,
+    } = arg;
+
+    let mut recips = collect_recipients(recipient, recipients_file)?;
+
+    if let Some(i) = identity {
+        let ParsedIdentity { recipient, .. } = RawIdentity::from(i.clone()).try_into()?;
+        recips.push(recipient);
+    }
+
+    if recips.is_empty() {
+        bail!("must provide at least one recipient or identity");
+    }
+
+    if output.is_none() && report::is_json() {
+        bail!("stdout is occupied by `--output json`, use -o");
+    }
+    if output.is_none() && io::stdout().is_terminal() {
+        bail!("refuse to write encrypted content to terminal, use -o or redirect stdout");
+    }
+
+    let encrypted = read_plain(input.as_deref())?.encrypt(recips.iter().map(|r| r.as_ref()))?;
+
+    match output {
+        Some(o) => {
+            encrypted
+                .write_atomic(Path::new(o), 0o644)
+                .wrap_err_with(|| eyre!("write encrypted file {o} error"))?;
+            info!("encrypted file written to {o}");
+            report::written(Path::new(o));
+        }
+        None => {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(encrypted.buf_ref())?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use std::{fmt, fs, str::FromStr};
+
+use eyre::{Context, Result, eyre};
+
+use crate::{
+    parser::{
+        header::{Header, ssh_key_tag},
+        recipient::gather_recipients,
+    },
+    profile::Profile,
+    util::report,
+};
+
+use super::InspectSubCmd;
+
+/// whether a candidate recipient is able to decrypt the file
+#[derive(Debug, PartialEq)]
+enum Access {
+    Likely,
+    No,
+    /// stanza doesn't reveal its recipient
+    Unknown,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Access::Likely => "likely",
+            Access::No => "no",
+            Access::Unknown => "unknown",
+        })
+    }
+}
+
+fn access_of(header: &Header, recip: &str) -> Access {
+    let mut stanzas = header.recipient_stanzas();
+    if let Some(tag) = ssh_key_tag(recip) {
+        if stanzas.any(|s| s.ssh_tag() == Some(tag.as_str())) {
+            Access::Likely
+        } else {
+            Access::No
+        }
+    } else if age::x25519::Recipient::from_str(recip).is_ok() {
+        if stanzas.any(|s| s.tag == "X25519") {
+            Access::Unknown
+        } else {
+            Access::No
+        }
+    } else if stanzas.any(|s| !s.is_native()) {
+        Access::Unknown
+    } else {
+        Access::No
+    }
+}
+
+pub fn inspect(arg: &InspectSubCmd, profiles: &[Profile]) -> Result<()> {
+    let InspectSubCmd {
+        file,
+        recipient,
+        recipients_file,
+    } = arg;
+
+    let content = fs::read(file).wrap_err_with(|| eyre!("read {file} error"))?;
+    let header = Header::parse(&content).wrap_err_with(|| eyre!("{file} is not an age file"))?;
+
+    let candidates: Vec<(String, Access)> = profiles
+        .iter()
+        .map(|p| {
+            (
+                p.settings.host_identifier.clone(),
+                p.settings.host_pubkey.clone(),
+            )
+        })
+        .chain(
+            gather_recipients(recipient, recipients_file)?
+                .into_iter()
+                .map(|r| (r.as_str().to_string(), r.as_str().to_string())),
+        )
+        .map(|(name, recip)| (name, access_of(&header, &recip)))
+        .collect();
+
+    if report::is_json() {
+        report::data(serde_json::json!({
+            "file": file,
+            "armored": header.armored,
+            "payloadLen": header.payload_len,
+            "stanzas": header
+                .recipient_stanzas()
+                .map(|s| serde_json::json!({ "type": s.tag, "keyTag": s.ssh_tag() }))
+                .collect::<Vec<_>>(),
+            "candidates": candidates
+                .iter()
+                .map(|(name, access)| serde_json::json!({ "name": name, "access": access.to_string() }))
+                .collect::<Vec<_>>(),
+        }));
+        return Ok(());
+    }
+
+    println!("file: {file}");
+    println!("armored: {}", if header.armored { "yes" } else { "no" });
+    println!("payload: {} bytes", header.payload_len);
+    println!("recipients: {}", header.recipient_stanzas().count());
+    header.recipient_stanzas().for_each(|s| println!("  {s}"));
+
+    if !candidates.is_empty() {
+        println!("candidates:");
+        candidates
+            .iter()
+            .for_each(|(name, access)| println!("  {name}: {access}"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::header::tests::encrypt_to;
+
+    #[test]
+    fn candidate_access() {
+        let ssh = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHsKLqeplhpW+uObz5dvMgjz1OxfM/XXUB+VHtZ6isGN alice@rust";
+        let other_ssh =
+            "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIAujj3ZrNYuQ/k5zZ+hD9lkB4lBYj1KZkcopRZJdmFOx";
+        let x25519 = "age1qyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqs3290gq";
+
+        let recip: age::ssh::Recipient = ssh.parse().unwrap();
+        let header = Header::parse(&encrypt_to(&[&recip], false)).unwrap();
+
+        assert_eq!(access_of(&header, ssh), Access::Likely);
+        assert_eq!(access_of(&header, other_ssh), Access::No);
+        assert_eq!(access_of(&header, x25519), Access::No);
+    }
+}
@@ -1,14 +1,31 @@
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 use eyre::{eyre, Context, ContextCompat};
 use log::info;
 use renc::CompleteProfile;
-use {argh::FromArgs, std::fmt::Debug};
+
+use crate::parser::recipient::collect_recipients;
+use crate::profile::MountStrategy;
+use crate::util::report::{self, Output};
+use {
+    argh::{FromArgs, SubCommand},
+    std::fmt::Debug,
+};
 
 mod check;
+mod decrypt;
 mod deploy;
 mod edit;
+mod encrypt;
+mod inspect;
+mod rekey;
 pub mod renc;
+mod rollback;
+mod status;
+pub mod verify;
 
 #[derive(FromArgs, PartialEq, Debug)]
 /// Vaultix cli | Secret manager for NixOS
@@ -21,15 +38,26 @@ pub struct Args {
     #[argh(option, short = 'f')]
     /// toplevel of flake repository
     flake_root: Option<String>,
+    #[argh(option, default = "Output::Text")]
+    /// print result document on stdout, `text` or `json`
+    output: Output,
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
 #[argh(subcommand)]
 enum SubCmd {
     Renc(RencSubCmd),
+    Rekey(RekeySubCmd),
     Edit(EditSubCmd),
+    Encrypt(EncryptSubCmd),
+    Decrypt(DecryptSubCmd),
     Check(CheckSubCmd),
+    Status(StatusSubCmd),
+    Inspect(InspectSubCmd),
     Deploy(DeploySubCmd),
+    Rollback(RollbackSubCmd),
+    SwitchGeneration(SwitchGenerationSubCmd),
+    Verify(VerifySubCmd),
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
@@ -44,6 +72,24 @@ pub struct RencSubCmd {
     cache: String,
 }
 
+#[derive(FromArgs, PartialEq, Debug)]
+/// Re-encrypt source secrets to new recipients
+#[argh(subcommand, name = "rekey")]
+pub struct RekeySubCmd {
+    #[argh(option, short = 'i')]
+    /// identity for decrypt secret
+    identity: String,
+    #[argh(option, short = 'r')]
+    /// new recipients for encrypt secrets
+    recipient: Vec<String>,
+    #[argh(option, short = 'R')]
+    /// file containing new recipients, one per line
+    recipients_file: Vec<String>,
+    #[argh(switch)]
+    /// also encrypt to recipients recorded next to each secret by edit
+    keep_recorded: bool,
+}
+
 #[derive(FromArgs, PartialEq, Debug, Clone)]
 /// Edit encrypted file
 #[argh(subcommand, name = "edit")]
@@ -57,6 +103,54 @@ pub struct EditSubCmd {
     #[argh(option, short = 'r')]
     /// recipients for encrypt secrets
     recipient: Vec<String>,
+    #[argh(option, short = 'R')]
+    /// file containing recipients, one per line
+    recipients_file: Vec<String>,
+    #[argh(switch)]
+    /// encrypt only to given recipients instead of adding them to recorded ones
+    reset_recipients: bool,
+    #[argh(switch)]
+    /// allow plaintext scratch file on disk backed temp dir
+    allow_disk_tmp: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Encrypt plaintext from file or stdin
+#[argh(subcommand, name = "encrypt")]
+pub struct EncryptSubCmd {
+    #[argh(positional)]
+    /// plaintext file, read stdin if omitted
+    input: Option<String>,
+    #[argh(option, short = 'i')]
+    /// identity whose recipient is also encrypted to
+    identity: Option<String>,
+    #[argh(option, short = 'r')]
+    /// recipients for encrypt secrets
+    recipient: Vec<String>,
+    #[argh(option, short = 'R')]
+    /// file containing recipients, one per line
+    recipients_file: Vec<String>,
+    #[argh(option, short = 'o')]
+    /// output file, write stdout if omitted
+    output: Option<String>,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Decrypt single secret to stdout or file
+#[argh(subcommand, name = "decrypt")]
+pub struct DecryptSubCmd {
+    #[argh(positional)]
+    /// encrypted file
+    file: String,
+    #[argh(option, short = 'i')]
+    /// identity for decrypt secret
+    identity: String,
+    #[argh(option, short = 'o')]
+    /// output file, write stdout if omitted
+    output: Option<String>,
+    #[argh(switch)]
+    /// write binary content to terminal anyway
+    force: bool,
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
@@ -66,6 +160,78 @@ pub struct DeploySubCmd {
     #[argh(switch, short = 'e')]
     /// deploy before users init
     early: bool,
+    #[argh(switch, short = 'k')]
+    /// link the new generation even if some secrets or templates failed
+    keep_going: bool,
+    #[argh(switch)]
+    /// print planned actions without mounting or writing anything
+    dry_run: bool,
+    #[argh(option)]
+    /// deploy into the system mounted at this dir, e.g. /mnt while nixos-install
+    root: Option<String>,
+    #[argh(option)]
+    /// override `mountStrategy` setting: ramfs, tmpfs or existing
+    mount: Option<MountStrategy>,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Link the decrypted dir to previous generation
+#[argh(subcommand, name = "rollback")]
+pub struct RollbackSubCmd {
+    #[argh(switch, short = 'e')]
+    /// operate on generations deployed before users init
+    early: bool,
+    #[argh(switch, short = 'l')]
+    /// list existing generations only
+    list: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Link the decrypted dir to specified generation
+#[argh(subcommand, name = "switch-generation")]
+pub struct SwitchGenerationSubCmd {
+    #[argh(positional)]
+    /// generation number
+    generation: usize,
+    #[argh(switch, short = 'e')]
+    /// operate on generations deployed before users init
+    early: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Check deployed files against the record of linked generation, exit 2 on drift
+#[argh(subcommand, name = "verify")]
+pub struct VerifySubCmd {
+    #[argh(switch, short = 'e')]
+    /// verify secrets deployed before users init
+    early: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// List re-encryption state of secrets of every host
+#[argh(subcommand, name = "status")]
+pub struct StatusSubCmd {
+    #[argh(option, short = 'c')]
+    /// cache dir of re-encrypted secrets
+    cache: String,
+    #[argh(switch)]
+    /// same as global `--output json`
+    json: bool,
+}
+
+#[derive(FromArgs, PartialEq, Debug)]
+/// Show age header of encrypted file without decrypting
+#[argh(subcommand, name = "inspect")]
+pub struct InspectSubCmd {
+    #[argh(positional)]
+    /// encrypted file
+    file: String,
+    #[argh(option, short = 'r')]
+    /// candidate recipients to check access for
+    recipient: Vec<String>,
+    #[argh(option, short = 'R')]
+    /// file containing candidate recipients, one per line
+    recipients_file: Vec<String>,
 }
 
 #[derive(FromArgs, PartialEq, Debug)]
@@ -73,10 +239,47 @@ pub struct DeploySubCmd {
 #[argh(subcommand, name = "check")]
 pub struct CheckSubCmd {}
 
+impl SubCmd {
+    fn name(&self) -> &'static str {
+        macro_rules! names {
+            ($($variant:ident => $cmd:ty),+ $(,)?) => {
+                match self {
+                    $(SubCmd::$variant(_) => <$cmd as SubCommand>::COMMAND.name,)+
+                }
+            };
+        }
+        names!(
+            Renc => RencSubCmd,
+            Rekey => RekeySubCmd,
+            Edit => EditSubCmd,
+            Encrypt => EncryptSubCmd,
+            Decrypt => DecryptSubCmd,
+            Check => CheckSubCmd,
+            Status => StatusSubCmd,
+            Inspect => InspectSubCmd,
+            Deploy => DeploySubCmd,
+            Rollback => RollbackSubCmd,
+            SwitchGeneration => SwitchGenerationSubCmd,
+            Verify => VerifySubCmd,
+        )
+    }
+}
+
 impl Args {
     /// Parse Command Args
     pub fn ayaya(&self) -> eyre::Result<()> {
+        let status_json = matches!(self.app, SubCmd::Status(StatusSubCmd { json: true, .. }));
+        if self.output == Output::Json || status_json {
+            report::start(self.app.name());
+        }
+        let res = self.run();
+        report::finish(&res);
+        res
+    }
+
+    fn run(&self) -> eyre::Result<()> {
         use super::profile::Profile;
+        use super::util::harden;
 
         let profile = || -> eyre::Result<Vec<Profile>> {
             self.profile
@@ -101,6 +304,7 @@ impl Args {
         match &self.app {
             SubCmd::Renc(RencSubCmd { identity, cache }) => {
                 info!("start re-encrypt secrets");
+                harden::disable_core_dump()?;
                 let profile = profile()?;
                 CompleteProfile::from_iter(&profile).renc(
                     flake_root,
@@ -108,18 +312,80 @@ impl Args {
                     cache.into(),
                 )
             }
-            SubCmd::Deploy(DeploySubCmd { early }) => {
+            SubCmd::Rekey(RekeySubCmd {
+                identity,
+                recipient,
+                recipients_file,
+                keep_recorded,
+            }) => {
+                info!("start rekey secrets");
+                harden::disable_core_dump()?;
+                let recips = collect_recipients(recipient, recipients_file)?;
+                let profile = profile()?;
+                CompleteProfile::from_iter(&profile).rekey(
+                    flake_root,
+                    identity.clone(),
+                    recips,
+                    *keep_recorded,
+                )
+            }
+            SubCmd::Deploy(d) => {
                 info!("deploying secrets");
+                harden::disable_core_dump()?;
+                let mut profile = profile()?;
+                let profile = profile
+                    .first_mut()
+                    .wrap_err_with(|| eyre!("deploy must provide one single profile"))?;
+                if let Some(root) = &d.root {
+                    profile.reroot(Path::new(root));
+                }
+                profile.deploy(d)
+            }
+            SubCmd::Rollback(RollbackSubCmd { early, list }) => {
+                let profile = profile()?;
+                let profile = profile
+                    .first()
+                    .wrap_err_with(|| eyre!("rollback must provide one single profile"))?;
+                if *list {
+                    profile.list_generations(*early)
+                } else {
+                    info!("rolling back secrets");
+                    profile.rollback(*early)
+                }
+            }
+            SubCmd::SwitchGeneration(SwitchGenerationSubCmd { generation, early }) => {
+                info!("switching secrets generation");
+                let profile = profile()?;
+                profile
+                    .first()
+                    .wrap_err_with(|| eyre!("switch-generation must provide one single profile"))?
+                    .switch_generation(*generation, *early)
+            }
+            SubCmd::Verify(VerifySubCmd { early }) => {
                 let profile = profile()?;
                 profile
                     .first()
-                    .wrap_err_with(|| eyre!("deploy must provide one single profile"))?
-                    .deploy(*early)
+                    .wrap_err_with(|| eyre!("verify must provide one single profile"))?
+                    .verify(*early)
             }
             SubCmd::Edit(e) => {
                 info!("editing secrets");
+                harden::disable_core_dump()?;
                 edit::edit(e.clone())
             }
+            SubCmd::Encrypt(e) => {
+                harden::disable_core_dump()?;
+                encrypt::encrypt(e)
+            }
+            SubCmd::Decrypt(d) => {
+                harden::disable_core_dump()?;
+                decrypt::decrypt(d)
+            }
+            SubCmd::Status(StatusSubCmd { cache, .. }) => {
+                let profile = profile()?;
+                CompleteProfile::from_iter(&profile).status(cache.into())
+            }
+            SubCmd::Inspect(i) => inspect::inspect(i, &profile()?),
             SubCmd::Check(_) => {
                 info!("start checking");
                 let profile = profile()?;
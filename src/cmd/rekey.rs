@@ -0,0 +1,169 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+use age::Recipient;
+use eyre::{Context, Result, bail, eyre};
+use log::{debug, info, warn};
+
+use crate::{
+    parser::{
+        identity::{ParsedIdentity, RawIdentity},
+        recipient::{build_recipients, read_sidecar, sidecar_of},
+    },
+    util::{
+        report,
+        secbuf::{AgeEnc, Decryptable, SecBuf},
+    },
+};
+
+use super::renc::CompleteProfile;
+
+/// every `.age` file below `dir`, skipping hidden entries and symlinks
+fn find_age_files(dir: &Path, found: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).wrap_err_with(|| eyre!("read {} fail", dir.display()))? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            find_age_files(&path, found)?;
+        } else if file_type.is_file() && path.extension().is_some_and(|e| e == "age") {
+            found.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// encrypt to `recips` only, plus recorded ones of the file if `keep_recorded`
+fn rekey_file(
+    path: &Path,
+    ident: &dyn age::Identity,
+    recips: &[Box<dyn Recipient + Send>],
+    keep_recorded: bool,
+) -> Result<()> {
+    let mode = fs::metadata(path)?.permissions().mode() & 0o7777;
+    let recorded = read_sidecar(path)?;
+    let sidecar = sidecar_of(path);
+    recorded.iter().for_each(|r| {
+        if keep_recorded {
+            info!("keeping {} recorded in {}", r.as_str(), sidecar.display())
+        } else {
+            warn!(
+                "not encrypting to {} recorded in {}, next edit will unless its line removed",
+                r.as_str(),
+                sidecar.display()
+            )
+        }
+    });
+    let recorded = if keep_recorded {
+        build_recipients(recorded)?
+    } else {
+        vec![]
+    };
+    fs::read(path)
+        .map(SecBuf::<AgeEnc>::new)?
+        .decrypt(ident)?
+        .encrypt(recips.iter().chain(recorded.iter()).map(|r| r.as_ref()))?
+        .write_atomic(path, mode)
+}
+
+impl CompleteProfile<'_> {
+    /**
+    re-encrypt source secrets to new recipients
+
+    `Secret.file` refers to the copy in nix store, the corresponding
+    file in repository is located by matching its content.
+    */
+    pub fn rekey(
+        &self,
+        flake_root: PathBuf,
+        identity: String,
+        recips: Vec<Box<dyn Recipient + Send>>,
+        keep_recorded: bool,
+    ) -> Result<()> {
+        if recips.is_empty() {
+            bail!("must provide at least one new recipient");
+        }
+
+        let sources: HashSet<&str> = self
+            .inner_ref()
+            .iter()
+            .flat_map(|p| p.secrets.values().map(|s| s.file.as_str()))
+            .collect();
+
+        let mut pending: HashMap<blake3::Hash, &str> = sources
+            .into_iter()
+            .map(|f| {
+                fs::read(f)
+                    .map(|c| (blake3::hash(&c), f))
+                    .wrap_err_with(|| eyre!("read source secret {f} error"))
+            })
+            .collect::<Result<_>>()?;
+
+        let mut candidates = vec![];
+        find_age_files(&flake_root, &mut candidates)?;
+
+        let mut targets = vec![];
+        for path in candidates {
+            let hash = blake3::hash(&fs::read(&path)?);
+            if let Some(store) = pending.get(&hash) {
+                debug!("{} found as {}", store, path.display());
+                targets.push((path, hash));
+            }
+        }
+        let found: HashSet<blake3::Hash> = targets.iter().map(|(_, h)| *h).collect();
+        pending.retain(|h, _| !found.contains(h));
+
+        if !pending.is_empty() {
+            let missing: Vec<&str> = pending.into_values().collect();
+            bail!(
+                "source of {} secret(s) not found under {}, is the repository clean?\n{}",
+                missing.len(),
+                flake_root.display(),
+                missing.join("\n")
+            );
+        }
+
+        let ParsedIdentity { identity, .. } = RawIdentity::from(identity).try_into()?;
+
+        let (ok, failed): (Vec<_>, Vec<_>) = targets
+            .iter()
+            .map(|(p, _)| (p, rekey_file(p, identity.as_ref(), &recips, keep_recorded)))
+            .partition(|(_, r)| r.is_ok());
+
+        ok.iter().for_each(|(p, _)| {
+            info!("rekeyed {}", p.display());
+            report::written(p);
+        });
+        failed.iter().for_each(|(p, r)| {
+            if let Err(e) = r {
+                report::failed(p.display().to_string(), Some(p), e)
+            }
+        });
+
+        if !failed.is_empty() {
+            let report = failed
+                .iter()
+                .filter_map(|(p, r)| r.as_ref().err().map(|e| format!("{}: {e:#}", p.display())))
+                .collect::<Vec<_>>()
+                .join("\n");
+            bail!(
+                "{} file(s) failed to rekey, {} rekeyed:\n{report}",
+                failed.len(),
+                ok.len()
+            );
+        }
+
+        if ok.is_empty() {
+            warn!("no secret found in profiles");
+        }
+        info!("{} file(s) rekeyed, run renc to regenerate cache", ok.len());
+        Ok(())
+    }
+}
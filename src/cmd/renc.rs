@@ -1,10 +1,13 @@
 use crate::{
     parser::identity::{ParsedIdentity, RawIdentity},
     profile::Profile,
-    util::secmap::{RencBuilder, RencCtx},
+    util::{
+        report,
+        secmap::{RencBuilder, RencCtx},
+    },
 };
 use eyre::{Result, bail};
-use log::error;
+use log::{error, info};
 use std::{fs, path::PathBuf};
 
 pub struct CompleteProfile<'a>(pub Vec<&'a Profile>);
@@ -51,13 +54,17 @@ impl<'a> CompleteProfile<'a> {
         let ctx = RencCtx::create(&self)?;
         let mut materia = RencBuilder::create(&self).build_inrepo(&ctx, cache_path.clone());
         materia.clean_outdated(cache_path)?;
-        materia.retain_noexist();
+        let skipped = materia.retain_noexist();
+        info!("{skipped} file(s) already re-encrypted, skipped");
+        report::skipped(skipped);
 
         let ParsedIdentity {
             identity,
             recipient: _,
         } = RawIdentity::from(identity).try_into()?;
 
-        materia.build_instance().makeup(&ctx, identity)
+        let written = materia.build_instance().makeup(&ctx, identity)?;
+        info!("{written} written, {skipped} skipped, 0 failed");
+        Ok(())
     }
 }
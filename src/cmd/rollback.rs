@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use eyre::{Result, bail, eyre};
+use log::{info, warn};
+
+use crate::{
+    profile::Profile,
+    util::{
+        generation::{Generations, Manifest},
+        report,
+    },
+};
+
+impl Profile {
+    /// print existing generations, mark the linked one
+    pub fn list_generations(&self, early: bool) -> Result<()> {
+        let generations = self.generations(early);
+        let linked = generations.linked(Path::new(self.decrypted_symlink(early)));
+        if report::is_json() {
+            report::data(serde_json::json!({
+                "generations": generations.list()?,
+                "current": linked,
+            }));
+            return Ok(());
+        }
+        generations.list()?.into_iter().for_each(|g| {
+            if Some(g) == linked {
+                println!("{g} (current)");
+            } else {
+                println!("{g}");
+            }
+        });
+        Ok(())
+    }
+
+    /// link the generation right before the current one
+    pub fn rollback(&self, early: bool) -> Result<()> {
+        let generations = self.generations(early);
+        let symlink = Path::new(self.decrypted_symlink(early));
+        let current = generations
+            .linked(symlink)
+            .ok_or_else(|| eyre!("{} is not linked to any generation", symlink.display()))?;
+
+        let Some(previous) = generations.list()?.into_iter().rfind(|g| *g < current) else {
+            bail!("no generation older than current {current} exists")
+        };
+
+        self.link_generation(&generations, previous, symlink)
+    }
+
+    /// link the given generation
+    pub fn switch_generation(&self, generation: usize, early: bool) -> Result<()> {
+        let generations = self.generations(early);
+        if !generations.list()?.contains(&generation) {
+            bail!("generation {generation} not found");
+        }
+        self.link_generation(
+            &generations,
+            generation,
+            Path::new(self.decrypted_symlink(early)),
+        )
+    }
+
+    fn link_generation(
+        &self,
+        generations: &Generations,
+        generation: usize,
+        symlink: &Path,
+    ) -> Result<()> {
+        info!("switching to generation {generation}");
+        generations.link(generation, symlink)?;
+        report::written(symlink);
+        report::data(serde_json::json!({ "current": generation }));
+
+        let manifest = Manifest::read_from(&generations.path_of(generation))?;
+        // symlink mode ones resolve through the switched link, so they are restored
+        let linked: Vec<&Path> = manifest
+            .items
+            .iter()
+            .filter_map(|i| i.link.as_ref().map(|l| l.path.as_path()))
+            .collect();
+        manifest
+            .external
+            .iter()
+            .filter(|p| !linked.contains(&p.as_path()))
+            .for_each(|p| {
+                warn!(
+                    "{} is deployed outside generation dir, content not restored",
+                    p.display()
+                )
+            });
+        Ok(())
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use eyre::{Context, Result, eyre};
+use serde::Serialize;
+
+use crate::util::{
+    report,
+    secmap::{RencBuilder, RencCtx},
+};
+
+use super::renc::CompleteProfile;
+
+#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+enum State {
+    /// re-encrypted file of current source and host key exists
+    Present,
+    /// source or host key changed since last renc
+    Stale,
+    /// cache file no secret refers to
+    Orphaned,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SecretStatus {
+    host: String,
+    id: Option<String>,
+    source: Option<String>,
+    hash: Option<String>,
+    cache: PathBuf,
+    in_store: bool,
+    state: State,
+}
+
+/// files in host cache dir, empty if not created yet
+fn list_host_cache(dir: &Path) -> Result<Vec<PathBuf>> {
+    match fs::read_dir(dir) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e).wrap_err_with(|| eyre!("read {} fail", dir.display())),
+        Ok(d) => Ok(d
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file())
+            .collect()),
+    }
+}
+
+fn print_table(rows: &[SecretStatus]) {
+    let cells: Vec<[String; 6]> = rows
+        .iter()
+        .map(|r| {
+            [
+                r.host.clone(),
+                r.id.clone().unwrap_or("-".into()),
+                format!("{:?}", r.state).to_lowercase(),
+                r.hash.clone().unwrap_or("-".into()),
+                r.source.clone().unwrap_or("-".into()),
+                r.cache.display().to_string(),
+            ]
+        })
+        .collect();
+    let header = ["HOST", "ID", "STATE", "HASH", "SOURCE", "CACHE"].map(String::from);
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| {
+            std::iter::once(&header)
+                .chain(cells.iter())
+                .map(|c| c[i].len())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    std::iter::once(&header).chain(cells.iter()).for_each(|c| {
+        let line = c
+            .iter()
+            .zip(&widths)
+            .map(|(s, w)| format!("{s:w$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        println!("{}", line.trim_end());
+    });
+}
+
+impl CompleteProfile<'_> {
+    /// list re-encryption state of every secret of every host
+    pub fn status(&self, cache_dir: PathBuf) -> Result<()> {
+        let ctx = RencCtx::create(self)?;
+
+        let store_of: HashMap<&str, &str> = self
+            .inner_ref()
+            .iter()
+            .map(|p| (p.host_identifier(), p.settings.cache_in_store.as_str()))
+            .collect();
+
+        let expected = RencBuilder::create(self)
+            .build_inrepo(&ctx, cache_dir.clone())
+            .inner();
+
+        let mut rows: Vec<SecretStatus> = expected
+            .iter()
+            .map(|((secret, host), path)| {
+                let hash = ctx
+                    .inner_ref()
+                    .get(secret)
+                    .expect("must have")
+                    .hash_with(host.recip())
+                    .to_string();
+                let in_store = store_of
+                    .get(host.id())
+                    .is_some_and(|s| Path::new(s).join(&hash).exists());
+                SecretStatus {
+                    host: host.id().to_string(),
+                    id: Some(secret.id.clone()),
+                    source: Some(secret.file.clone()),
+                    hash: Some(hash),
+                    cache: path.path.clone(),
+                    in_store,
+                    state: if path.path.exists() {
+                        State::Present
+                    } else {
+                        State::Stale
+                    },
+                }
+            })
+            .collect();
+
+        let referred: HashSet<&PathBuf> = expected.values().map(|p| &p.path).collect();
+        for host in store_of.keys() {
+            list_host_cache(&cache_dir.join(host))?
+                .into_iter()
+                .filter(|p| !referred.contains(p))
+                .for_each(|p| {
+                    rows.push(SecretStatus {
+                        host: host.to_string(),
+                        id: None,
+                        source: None,
+                        hash: p.file_name().map(|n| n.to_string_lossy().to_string()),
+                        cache: p,
+                        in_store: false,
+                        state: State::Orphaned,
+                    })
+                });
+        }
+
+        rows.sort_by(|a, b| (&a.host, &a.state, &a.id).cmp(&(&b.host, &b.state, &b.id)));
+
+        report::processed(rows.len());
+        if report::is_json() {
+            report::data(&rows);
+        } else {
+            print_table(&rows);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,351 @@
+use std::{
+    fmt, fs,
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use eyre::{Context, Result, bail, eyre};
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::{
+    profile::Profile,
+    util::{
+        generation::{HashKey, Manifest, ManifestItem, ManifestLink},
+        report,
+    },
+};
+
+/// deployed files differ from what was recorded, exits with 2
+#[derive(Debug)]
+pub struct Drift(pub usize);
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} deployed item(s) drifted", self.0)
+    }
+}
+
+impl std::error::Error for Drift {}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ItemState {
+    kind: String,
+    id: String,
+    path: Option<PathBuf>,
+    drift: Vec<String>,
+}
+
+/// differences between the file at recorded path and the record
+fn drift_of(item: &ManifestItem, key: &HashKey) -> Vec<String> {
+    let meta = match fs::symlink_metadata(&item.path) {
+        Err(e) => return vec![format!("unreadable: {e}")],
+        Ok(m) if !m.file_type().is_file() => return vec!["not a regular file".into()],
+        Ok(m) => m,
+    };
+    let mut ret = vec![];
+    let mode = meta.permissions().mode() & 0o7777;
+    if mode != item.mode {
+        ret.push(format!("mode {mode:04o}, expect {:04o}", item.mode));
+    }
+    if meta.uid() != item.uid {
+        ret.push(format!("uid {}, expect {}", meta.uid(), item.uid));
+    }
+    if meta.gid() != item.gid {
+        ret.push(format!("gid {}, expect {}", meta.gid(), item.gid));
+    }
+    match fs::read(&item.path).map(zeroize::Zeroizing::new) {
+        Err(e) => ret.push(format!("unreadable: {e}")),
+        Ok(c) if key.hash(&c) != item.hash => ret.push("content changed".into()),
+        Ok(_) => (),
+    }
+    if let Some(link) = &item.link {
+        ret.extend(drift_of_link(link));
+    }
+    ret
+}
+
+/// custom path of symlink mode should still link to the recorded target
+fn drift_of_link(link: &ManifestLink) -> Option<String> {
+    let path = link.path.display();
+    match fs::symlink_metadata(&link.path) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Some(format!("link {path} missing"));
+        }
+        Err(e) => return Some(format!("link {path} unreadable: {e}")),
+        Ok(m) if !m.file_type().is_symlink() => {
+            return Some(format!("link {path} is not a symlink"));
+        }
+        Ok(_) => (),
+    }
+    match fs::read_link(&link.path) {
+        Err(e) => Some(format!("link {path} unreadable: {e}")),
+        Ok(t) if t != link.target => Some(format!(
+            "link {path} points to {}, expect {}",
+            t.display(),
+            link.target.display()
+        )),
+        Ok(_) => None,
+    }
+}
+
+impl Profile {
+    /**
+    check files of the linked generation against its manifest, and that
+    every secret and template of profile was deployed
+    */
+    pub fn verify(&self, early: bool) -> Result<()> {
+        if self.before_userborn.is_empty() && early {
+            info!("nothing deployed before userborn. finish");
+            return Ok(());
+        }
+        let generations = self.generations(early);
+        let symlink = Path::new(self.decrypted_symlink(early));
+        let generation = generations
+            .linked(symlink)
+            .ok_or_else(|| eyre!("{} is not linked to any generation", symlink.display()))?;
+        let manifest = Manifest::read_from(&generations.path_of(generation))?;
+        if manifest.items.is_empty() {
+            bail!("generation {generation} has no record of deployed items, deploy again to verify")
+        }
+        let key = HashKey::read(Path::new(self.decrypted_mount_point()))?
+            .ok_or_else(|| eyre!("hash key not found in {}", self.decrypted_mount_point()))
+            .wrap_err_with(|| eyre!("generation {generation} could not be verified"))?;
+        info!("verifying generation {generation}");
+
+        let if_early = |id: &String| self.before_userborn.contains(id) == early;
+        let expected = self
+            .secrets
+            .keys()
+            .filter(|id| if_early(id))
+            .map(|id| ("secret", id))
+            .chain(
+                self.templates
+                    .keys()
+                    .filter(|id| if_early(id))
+                    .map(|id| ("template", id)),
+            );
+
+        let mut states: Vec<ItemState> = manifest
+            .items
+            .iter()
+            .map(|i| ItemState {
+                kind: i.kind.clone(),
+                id: i.id.clone(),
+                path: Some(i.path.clone()),
+                drift: drift_of(i, &key),
+            })
+            .collect();
+        states.extend(
+            expected
+                .filter(|(kind, id)| manifest.item(kind, id).is_none())
+                .map(|(kind, id)| ItemState {
+                    kind: kind.to_string(),
+                    id: id.clone(),
+                    path: None,
+                    drift: vec![format!("not deployed in generation {generation}")],
+                }),
+        );
+        states.sort_by(|a, b| (&a.kind, &a.id).cmp(&(&b.kind, &b.id)));
+
+        let drifted: Vec<&ItemState> = states.iter().filter(|s| !s.drift.is_empty()).collect();
+        report::processed(states.len() - drifted.len());
+        drifted.iter().for_each(|s| {
+            let cause = eyre!("{}", s.drift.join(", "));
+            warn!("{} {}: {cause}", s.kind, s.id);
+            report::failed(format!("{} {}", s.kind, s.id), s.path.as_deref(), &cause);
+        });
+
+        if report::is_json() {
+            report::data(&states);
+        } else {
+            drifted.iter().for_each(|s| {
+                println!(
+                    "{} {} {}: {}",
+                    s.kind,
+                    s.id,
+                    s.path
+                        .as_ref()
+                        .map_or("-".into(), |p| p.display().to_string()),
+                    s.drift.join(", ")
+                )
+            });
+        }
+
+        if !drifted.is_empty() {
+            return Err(Drift(drifted.len()).into());
+        }
+        info!("{} item(s) verified", states.len());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("vaultix-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// write `content` to `path` with mode 0400 and record it
+    fn deployed(path: &Path, content: &[u8], key: &HashKey) -> ManifestItem {
+        fs::write(path, content).unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(0o400)).unwrap();
+        let meta = fs::metadata(path).unwrap();
+        ManifestItem {
+            kind: "secret".into(),
+            id: path.file_name().unwrap().to_string_lossy().into(),
+            path: path.to_path_buf(),
+            mode: 0o400,
+            uid: meta.uid(),
+            gid: meta.gid(),
+            hash: key.hash(content),
+            link: None,
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn file_drift() {
+        let dir = test_dir("drift");
+        let key = HashKey::init(&dir).unwrap();
+        let mut item = deployed(&dir.join("a"), b"hunter2", &key);
+        assert!(drift_of(&item, &key).is_empty());
+
+        fs::set_permissions(&item.path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(drift_of(&item, &key), ["mode 0644, expect 0400"]);
+        fs::write(&item.path, b"hunter3").unwrap();
+        assert_eq!(
+            drift_of(&item, &key),
+            ["mode 0644, expect 0400", "content changed"]
+        );
+
+        let uid = item.uid;
+        item = deployed(&dir.join("b"), b"hunter2", &key);
+        item.uid = uid + 1;
+        assert_eq!(
+            drift_of(&item, &key),
+            [format!("uid {uid}, expect {}", uid + 1)]
+        );
+
+        item.path = dir.join("missing");
+        assert!(drift_of(&item, &key)[0].starts_with("unreadable: "));
+        item.path = dir.clone();
+        assert_eq!(drift_of(&item, &key), ["not a regular file"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn link_drift() {
+        let dir = test_dir("link-drift");
+        let key = HashKey::init(&dir).unwrap();
+        let target = dir.join("run/a");
+        let link = ManifestLink {
+            path: dir.join("a"),
+            target: target.clone(),
+        };
+        let p = link.path.display();
+        assert_eq!(drift_of_link(&link), Some(format!("link {p} missing")));
+
+        fs::write(&link.path, b"plain").unwrap();
+        assert_eq!(
+            drift_of_link(&link),
+            Some(format!("link {p} is not a symlink"))
+        );
+
+        fs::remove_file(&link.path).unwrap();
+        symlink("/elsewhere", &link.path).unwrap();
+        assert_eq!(
+            drift_of_link(&link),
+            Some(format!(
+                "link {p} points to /elsewhere, expect {}",
+                target.display()
+            ))
+        );
+
+        fs::remove_file(&link.path).unwrap();
+        symlink(&target, &link.path).unwrap();
+        assert_eq!(drift_of_link(&link), None);
+
+        // appended to drift of the file it links to
+        let item = ManifestItem {
+            link: Some(ManifestLink {
+                path: dir.join("b"),
+                target,
+            }),
+            ..deployed(&dir.join("c"), b"hunter2", &key)
+        };
+        assert_eq!(
+            drift_of(&item, &key),
+            [format!("link {} missing", dir.join("b").display())]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn drifted_items_counted() {
+        let dir = test_dir("verify");
+        let gen_dir = dir.join("d/normal/0");
+        fs::create_dir_all(&gen_dir).unwrap();
+        let key = HashKey::init(&dir.join("d")).unwrap();
+        symlink(&gen_dir, dir.join("run")).unwrap();
+
+        let manifest = Manifest {
+            external: vec![],
+            items: vec![deployed(&gen_dir.join("a"), b"hunter2", &key)],
+        };
+        manifest.write_to(&gen_dir).unwrap();
+
+        let secret = |id: &str| {
+            serde_json::json!({
+                "id": id, "file": "", "name": id, "path": dir.join("run").join(id),
+                "owner": "root", "group": "root", "mode": "0400",
+                "insert": {}, "cleanPlaceholder": false,
+            })
+        };
+        let profile = |secrets: serde_json::Value| -> Profile {
+            serde_json::from_value(serde_json::json!({
+                "settings": {
+                    "decryptedDir": dir.join("run"),
+                    "decryptedDirForUser": dir.join("run-for-user"),
+                    "decryptedMountPoint": dir.join("d"),
+                    "hostIdentifier": "h",
+                    "hostPubkey": "",
+                    "hostKeys": [],
+                    "cacheInStore": "",
+                },
+                "secrets": secrets,
+                "templates": {},
+                "placeholder": {},
+                "beforeUserborn": [],
+            }))
+            .unwrap()
+        };
+
+        profile(serde_json::json!({ "a": secret("a") }))
+            .verify(false)
+            .unwrap();
+
+        fs::set_permissions(gen_dir.join("a"), fs::Permissions::from_mode(0o440)).unwrap();
+        let err = profile(serde_json::json!({ "a": secret("a"), "b": secret("b") }))
+            .verify(false)
+            .unwrap_err();
+        // main exits with 2 on it
+        let drift = err.downcast_ref::<Drift>().unwrap();
+        assert_eq!(drift.0, 2);
+        assert_eq!(drift.to_string(), "2 deployed item(s) drifted");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
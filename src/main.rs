@@ -1,15 +1,21 @@
 #![feature(iterator_try_collect)]
-use cmd::Args;
+use cmd::{Args, verify::Drift};
 use eyre::Result;
 use simple_logger::SimpleLogger;
 
 mod cmd;
 mod util {
+    pub mod atomic;
     pub mod callback;
+    pub mod generation;
+    pub mod harden;
     pub mod makeup;
+    pub mod memfs;
+    pub mod report;
     pub mod secbuf;
     pub mod secmap;
     pub mod set_owner_group;
+    pub mod shred;
 }
 mod parser;
 mod profile;
@@ -22,5 +28,10 @@ fn main() -> Result<()> {
         .init()?;
 
     let args: Args = argh::from_env();
-    args.ayaya()
+    args.ayaya().inspect_err(|e| {
+        if e.downcast_ref::<Drift>().is_some() {
+            eprintln!("Error: {e:?}");
+            std::process::exit(2)
+        }
+    })
 }
@@ -0,0 +1,251 @@
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD},
+};
+use std::fmt;
+
+use eyre::{Result, eyre};
+use nom::{
+    IResult,
+    bytes::complete::{tag, take_while, take_while1},
+    character::complete::newline,
+    multi::{many0, separated_list1},
+    sequence::{preceded, terminated},
+};
+use sha2::{Digest, Sha256};
+
+const VERSION_LINE: &[u8] = b"age-encryption.org/v1\n";
+const ARMOR_BEGIN: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+const ARMOR_END: &str = "-----END AGE ENCRYPTED FILE-----";
+const BODY_COLUMNS: usize = 64;
+
+/// recipient stanza in age header
+#[derive(Debug, PartialEq)]
+pub struct Stanza {
+    pub tag: String,
+    pub args: Vec<String>,
+    pub body: Vec<u8>,
+}
+
+impl Stanza {
+    /// key tag of ssh stanza, first 4 bytes of sha256 of the ssh public key
+    pub fn ssh_tag(&self) -> Option<&str> {
+        match self.tag.as_str() {
+            "ssh-ed25519" | "ssh-rsa" => self.args.first().map(String::as_str),
+            _ => None,
+        }
+    }
+
+    /// whether the stanza type is one of the natively supported
+    pub fn is_native(&self) -> bool {
+        matches!(
+            self.tag.as_str(),
+            "X25519" | "ssh-ed25519" | "ssh-rsa" | "scrypt"
+        )
+    }
+}
+
+impl fmt::Display for Stanza {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tag.as_str() {
+            "X25519" => write!(f, "X25519"),
+            "ssh-ed25519" | "ssh-rsa" => write!(
+                f,
+                "{} (key tag {})",
+                self.tag,
+                self.ssh_tag().unwrap_or("missing")
+            ),
+            "scrypt" => write!(
+                f,
+                "scrypt (passphrase, work factor {})",
+                self.args.get(1).map_or("missing", String::as_str)
+            ),
+            t => write!(f, "{t} (plugin)"),
+        }
+    }
+}
+
+/// key tag age uses to identify ssh recipient in stanza, from
+/// `ssh-ed25519 AAAA... [comment]` format public key
+pub fn ssh_key_tag(pubkey: &str) -> Option<String> {
+    let mut parts = pubkey.split_whitespace();
+    if !matches!(parts.next()?, "ssh-ed25519" | "ssh-rsa") {
+        return None;
+    }
+    let wire = STANDARD.decode(parts.next()?).ok()?;
+    Some(STANDARD_NO_PAD.encode(&Sha256::digest(wire)[..4]))
+}
+
+/// age header of an encrypted file, parsed without decrypting
+#[derive(Debug)]
+pub struct Header {
+    pub stanzas: Vec<Stanza>,
+    pub armored: bool,
+    /// bytes after header, encrypted payload including nonce
+    pub payload_len: usize,
+}
+
+fn arbitrary_string(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    take_while1(|c: u8| (33..=126).contains(&c))(input)
+}
+
+fn b64_line(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    terminated(
+        take_while(|c: u8| c.is_ascii_alphanumeric() || c == b'+' || c == b'/'),
+        newline,
+    )(input)
+}
+
+/// wrapped base64 lines, ends with the first line shorter than 64 columns
+fn stanza_body(mut input: &[u8]) -> IResult<&[u8], Vec<u8>> {
+    let mut encoded = vec![];
+    loop {
+        let (rest, line) = b64_line(input)?;
+        encoded.extend_from_slice(line);
+        input = rest;
+        if line.len() < BODY_COLUMNS {
+            break;
+        }
+    }
+    match STANDARD_NO_PAD.decode(&encoded) {
+        Ok(body) => Ok((input, body)),
+        Err(_) => Err(nom::Err::Failure(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Verify,
+        ))),
+    }
+}
+
+fn stanza(input: &[u8]) -> IResult<&[u8], Stanza> {
+    let (input, mut parts) = preceded(
+        tag(b"-> "),
+        terminated(separated_list1(tag(b" "), arbitrary_string), newline),
+    )(input)?;
+    let (input, body) = stanza_body(input)?;
+    let to_string = |b: &[u8]| String::from_utf8_lossy(b).to_string();
+    let tag = to_string(parts.remove(0));
+    Ok((
+        input,
+        Stanza {
+            tag,
+            args: parts.into_iter().map(to_string).collect(),
+            body,
+        },
+    ))
+}
+
+fn header(input: &[u8]) -> IResult<&[u8], Vec<Stanza>> {
+    let (input, stanzas) = preceded(tag(VERSION_LINE), many0(stanza))(input)?;
+    let (input, _) = terminated(preceded(tag(b"--- "), arbitrary_string), newline)(input)?;
+    Ok((input, stanzas))
+}
+
+fn dearmor(content: &[u8]) -> Result<Vec<u8>> {
+    let text = std::str::from_utf8(content).map_err(|_| eyre!("armored file is not utf-8"))?;
+    let encoded: String = text
+        .lines()
+        .map(str::trim)
+        .skip_while(|l| *l != ARMOR_BEGIN)
+        .skip(1)
+        .take_while(|l| *l != ARMOR_END)
+        .collect();
+    STANDARD
+        .decode(encoded)
+        .map_err(|e| eyre!("decode armor fail: {e}"))
+}
+
+impl Header {
+    pub fn parse(content: &[u8]) -> Result<Self> {
+        let armored = content
+            .trim_ascii_start()
+            .starts_with(ARMOR_BEGIN.as_bytes());
+        let binary = if armored {
+            dearmor(content)?
+        } else {
+            content.to_vec()
+        };
+        let (payload, stanzas) =
+            header(&binary).map_err(|e| eyre!("parse age header fail: {e:?}"))?;
+        Ok(Self {
+            stanzas,
+            armored,
+            payload_len: payload.len(),
+        })
+    }
+
+    /// stanzas except the random `*-grease` ones added by age
+    pub fn recipient_stanzas(&self) -> impl Iterator<Item = &Stanza> {
+        self.stanzas.iter().filter(|s| !s.tag.ends_with("-grease"))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    /// `Hello world!` encrypted to given recipients
+    pub(crate) fn encrypt_to(recips: &[&dyn age::Recipient], armor: bool) -> Vec<u8> {
+        let encryptor = age::Encryptor::with_recipients(recips.iter().copied()).unwrap();
+        let mut out = vec![];
+        let mut writer = encryptor.wrap_output(&mut out).unwrap();
+        writer.write_all(b"Hello world!").unwrap();
+        writer.finish().unwrap();
+        if !armor {
+            return out;
+        }
+        let encoded = STANDARD.encode(out);
+        let lines: Vec<&str> = encoded
+            .as_bytes()
+            .chunks(BODY_COLUMNS)
+            .map(|c| std::str::from_utf8(c).unwrap())
+            .collect();
+        format!("{ARMOR_BEGIN}\n{}\n{ARMOR_END}\n", lines.join("\n")).into_bytes()
+    }
+
+    #[test]
+    fn parse_header() {
+        let recips: Vec<_> = (0..3)
+            .map(|_| age::x25519::Identity::generate().to_public())
+            .collect();
+        let recips: Vec<&dyn age::Recipient> = recips.iter().map(|r| r as _).collect();
+        for armor in [false, true] {
+            let h = Header::parse(&encrypt_to(&recips, armor)).unwrap();
+            assert_eq!(h.armored, armor);
+            // 16 bytes nonce, 12 bytes plaintext and 16 bytes tag
+            assert_eq!(h.payload_len, 16 + 12 + 16);
+            assert_eq!(h.recipient_stanzas().count(), 3);
+            assert!(
+                h.recipient_stanzas()
+                    .all(|s| s.tag == "X25519" && s.args.len() == 1 && s.body.len() == 32)
+            );
+        }
+        assert!(Header::parse(b"age-encryption.org/v1\n-> X25519\n").is_err());
+    }
+
+    #[test]
+    fn stanza_with_full_body_line() {
+        let body = [7u8; 48];
+        let encoded = STANDARD_NO_PAD.encode(body);
+        let input = format!("-> test a b\n{encoded}\n\n");
+        let (rest, s) = stanza(input.as_bytes()).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(s.tag, "test");
+        assert_eq!(s.args, vec!["a", "b"]);
+        assert_eq!(s.body, body);
+    }
+
+    #[test]
+    fn ssh_stanza_tag() {
+        let pubkey = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHsKLqeplhpW+uObz5dvMgjz1OxfM/XXUB+VHtZ6isGN alice@rust";
+        let recip: age::ssh::Recipient = pubkey.parse().unwrap();
+
+        let h = Header::parse(&encrypt_to(&[&recip], false)).unwrap();
+        let stanza = h.recipient_stanzas().next().unwrap();
+        assert_eq!(stanza.ssh_tag(), ssh_key_tag(pubkey).as_deref());
+        assert!(
+            ssh_key_tag("age1qyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqs3290gq").is_none()
+        );
+    }
+}
@@ -1,3 +1,4 @@
+pub mod header;
 pub mod identity;
 mod permission;
 pub mod recipient;
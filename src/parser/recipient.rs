@@ -1,3 +1,8 @@
+use std::{
+    iter,
+    path::{Path, PathBuf},
+};
+
 use age::{Callbacks, EncryptError, Recipient, plugin, ssh, x25519};
 use eyre::{Context, bail, eyre};
 use log::trace;
@@ -5,9 +10,15 @@ use serde::Deserialize;
 
 use crate::util::callback::UiCallbacks;
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Hash)]
 pub struct RecipString(String);
 
+impl RecipString {
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
 impl From<String> for RecipString {
     fn from(s: String) -> Self {
         Self(s)
@@ -44,6 +55,98 @@ impl TryInto<Box<dyn Recipient + Send>> for RecipString {
     }
 }
 
+/// recipients in age recipients file format: one per line,
+/// blank lines and lines starting with `#` ignored
+pub fn parse_recipients_file(content: &str) -> Vec<RecipString> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| RecipString(l.to_string()))
+        .collect()
+}
+
+/// recipient strings from `-r` and `-R` files, in given order
+pub fn gather_recipients(
+    recipients: &[String],
+    recipients_files: &[String],
+) -> eyre::Result<Vec<RecipString>> {
+    let from_files = recipients_files
+        .iter()
+        .map(|f| {
+            std::fs::read_to_string(f)
+                .wrap_err_with(|| eyre!("read recipients file {f} error"))
+                .map(|c| parse_recipients_file(&c))
+        })
+        .collect::<eyre::Result<Vec<_>>>()?;
+
+    Ok(recipients
+        .iter()
+        .cloned()
+        .map(RecipString)
+        .chain(from_files.into_iter().flatten())
+        .collect())
+}
+
+pub fn build_recipients(
+    recips: impl IntoIterator<Item = RecipString>,
+) -> eyre::Result<Vec<Box<dyn Recipient + Send>>> {
+    recips
+        .into_iter()
+        .map(|r| {
+            let s = r.0.clone();
+            TryInto::<Box<dyn Recipient + Send>>::try_into(r)
+                .wrap_err_with(|| eyre!("parse recipient {s} fail"))
+        })
+        .collect()
+}
+
+/// recipients from `-r` strings and `-R` files, in given order
+pub fn collect_recipients(
+    recipients: &[String],
+    recipients_files: &[String],
+) -> eyre::Result<Vec<Box<dyn Recipient + Send>>> {
+    build_recipients(gather_recipients(recipients, recipients_files)?)
+}
+
+/// `<file>.recipients`, recording recipients the secret is encrypted to
+/// besides the editing identity
+pub fn sidecar_of(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_owned();
+    name.push(".recipients");
+    PathBuf::from(name)
+}
+
+/// recorded recipients of `file`, empty if not recorded
+pub fn read_sidecar(file: &Path) -> eyre::Result<Vec<RecipString>> {
+    let sidecar = sidecar_of(file);
+    match std::fs::read_to_string(&sidecar) {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(vec![]),
+        Err(e) => Err(e).wrap_err_with(|| eyre!("read {} error", sidecar.display())),
+        Ok(c) => Ok(parse_recipients_file(&c)),
+    }
+}
+
+/// record recipients of `file`, the sidecar is removed if none
+pub fn write_sidecar(file: &Path, recips: &[RecipString]) -> eyre::Result<()> {
+    let sidecar = sidecar_of(file);
+    if recips.is_empty() {
+        return match std::fs::remove_file(&sidecar) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            r => r.wrap_err_with(|| eyre!("remove {} error", sidecar.display())),
+        };
+    }
+    let content = iter::once(format!(
+        "# recipients of {}, managed by vaultix edit",
+        file.file_name().unwrap_or_default().to_string_lossy()
+    ))
+    .chain(recips.iter().map(|r| r.0.clone()))
+    .collect::<Vec<_>>()
+    .join("\n")
+        + "\n";
+    std::fs::write(&sidecar, content).wrap_err_with(|| eyre!("write {} error", sidecar.display()))
+}
+
 fn build_plugin_recip(
     plugin_recip: &plugin::Recipient,
     callbacks: impl Callbacks,
@@ -66,6 +169,37 @@ fn build_plugin_recip(
 
 #[cfg(test)]
 mod tests {
+    use std::path::Path;
+
+    use super::{parse_recipients_file, sidecar_of};
+
+    #[test]
+    fn sidecar_path() {
+        assert_eq!(
+            sidecar_of(Path::new("./secrets/foo.age")),
+            Path::new("./secrets/foo.age.recipients")
+        );
+    }
+
+    #[test]
+    fn recipients_file() {
+        let content = "# admins\n\
+            age1qyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqs3290gq\n\
+            \n   \n\
+            ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHsKLqeplhpW+uObz5dvMgjz1OxfM/XXUB+VHtZ6isGN alice@rust  \n\
+            #age1yubikey1q2aucts9c72rmvnysgczu2zuvtvysmg2hkpvqld9qt5kyvyj6dd8gaujk62\n";
+        let parsed: Vec<String> = parse_recipients_file(content)
+            .into_iter()
+            .map(|r| r.0)
+            .collect();
+        assert_eq!(
+            parsed,
+            vec![
+                "age1qyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqszqgpqyqs3290gq",
+                "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIHsKLqeplhpW+uObz5dvMgjz1OxfM/XXUB+VHtZ6isGN alice@rust",
+            ]
+        );
+    }
 
     // comment since CI/CD doesn't have plugin installed
     // use super::*;
@@ -2,7 +2,8 @@ pub mod placeholder;
 pub mod template;
 
 use serde::Deserialize;
-use std::{collections::HashMap, hash::Hash, hash::Hasher};
+use std::{collections::HashMap, hash::Hash, hash::Hasher, path::PathBuf, str::FromStr};
+use strum_macros::Display;
 
 pub type SecretSet = HashMap<String, Secret>;
 pub type TemplateSet = HashMap<String, Template>;
@@ -46,6 +47,15 @@ pub struct Secret {
     pub path: String,
     pub insert: InsertSet,
     pub clean_placeholder: bool,
+    /// custom `path` links into decrypted dir instead of holding the plaintext
+    #[serde(default)]
+    pub symlink: bool,
+    #[serde(default)]
+    pub restart_units: Vec<String>,
+    #[serde(default)]
+    pub reload_units: Vec<String>,
+    #[serde(default)]
+    pub parent_dir: ParentDir,
 }
 
 #[derive(Debug, Deserialize, Clone, Hash, Eq, PartialEq, Default)]
@@ -58,6 +68,12 @@ pub struct Template {
     pub mode: String,
     pub owner: String,
     pub path: String,
+    #[serde(default)]
+    pub restart_units: Vec<String>,
+    #[serde(default)]
+    pub reload_units: Vec<String>,
+    #[serde(default)]
+    pub parent_dir: ParentDir,
 }
 
 #[derive(Debug, Deserialize, Clone, Hash, Eq, PartialEq, Default)]
@@ -77,6 +93,72 @@ pub struct Settings {
     pub host_pubkey: String,
     pub host_keys: Vec<HostKey>,
     pub cache_in_store: String,
+    #[serde(default = "default_keep_generations")]
+    pub keep_generations: usize,
+    #[serde(default)]
+    pub mount_strategy: MountStrategy,
+    /// `size=` of tmpfs, only used by tmpfs mount strategy
+    #[serde(default)]
+    pub tmpfs_size: Option<String>,
+    /// alternate root all paths above are under, set by `deploy --root`
+    #[serde(skip)]
+    pub root: Option<PathBuf>,
+}
+
+fn default_keep_generations() -> usize {
+    1
+}
+
+/// how missing parent directories of a custom `path` are created
+#[derive(Debug, Deserialize, Clone, Hash, Eq, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ParentDir {
+    pub create: bool,
+    pub mode: String,
+    pub owner: String,
+    pub group: String,
+    /// create even if on a filesystem not ramfs or tmpfs
+    pub allow_disk: bool,
+}
+
+impl Default for ParentDir {
+    fn default() -> Self {
+        Self {
+            create: false,
+            mode: "0755".into(),
+            owner: "root".into(),
+            group: "root".into(),
+            allow_disk: false,
+        }
+    }
+}
+
+/// how decrypted mount point is made memory backed
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default, Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum MountStrategy {
+    /// mount a ramfs, which never swaps
+    #[default]
+    Ramfs,
+    /// mount a tmpfs, with `noswap` if kernel supports
+    Tmpfs,
+    /// use the directory as is, after checking it is ramfs or tmpfs
+    Existing,
+}
+
+impl FromStr for MountStrategy {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ramfs" => Ok(Self::Ramfs),
+            "tmpfs" => Ok(Self::Tmpfs),
+            "existing" => Ok(Self::Existing),
+            o => Err(format!(
+                "unknown mount strategy `{o}`, expect `ramfs`, `tmpfs` or `existing`"
+            )),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -0,0 +1,115 @@
+/*!
+replace files and symlinks atomically through a temporary sibling.
+
+Temporary names are random and always freshly created, never reused or
+followed, so a symlink planted in a directory writable by others can't
+redirect what is written nor the following chmod and chown.
+*/
+use std::{
+    fs::{self, File, OpenOptions, Permissions},
+    io::{self, Read, Write},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use eyre::{Context, Result, bail, eyre};
+use log::{debug, trace};
+
+use super::set_owner_group::set_owner_and_group;
+
+/// run `create` on a fresh random path next to `dst`, retry if it exists
+fn create_beside<T>(
+    dst: &Path,
+    mut create: impl FnMut(&Path) -> io::Result<T>,
+) -> Result<(T, PathBuf)> {
+    let name = dst
+        .file_name()
+        .ok_or_else(|| eyre!("invalid destination {}", dst.display()))?;
+    for _ in 0..8 {
+        let mut suffix = [0u8; 8];
+        File::open("/dev/urandom")
+            .and_then(|mut f| f.read_exact(&mut suffix))
+            .wrap_err_with(|| eyre!("generate temporary name fail"))?;
+        let tmp = dst.with_file_name(format!(
+            ".{}.{}.tmp",
+            name.to_string_lossy(),
+            hex::encode(suffix)
+        ));
+        match create(&tmp) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                debug!("{} exists, retry another name", tmp.display());
+            }
+            r => {
+                return r
+                    .map(|t| (t, tmp.clone()))
+                    .wrap_err_with(|| eyre!("create {} fail", tmp.display()));
+            }
+        }
+    }
+    bail!("no unused temporary name next to {}", dst.display())
+}
+
+/// write `content` with `mode` and optionally `(uid, gid)`, then rename over `dst`
+pub fn write_file(dst: &Path, content: &[u8], mode: u32, owner: Option<(u32, u32)>) -> Result<()> {
+    let (mut file, tmp) = create_beside(dst, |tmp| {
+        OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            // never more permissive than owner only before chmod below
+            .mode(mode & 0o600)
+            .custom_flags(libc::O_NOFOLLOW)
+            .open(tmp)
+    })?;
+
+    let mut write_tmp = || -> Result<()> {
+        let permissions = Permissions::from_mode(mode);
+        trace!("apply file permission: {permissions:?}");
+        file.set_permissions(permissions)?;
+        if let Some((uid, gid)) = owner {
+            set_owner_and_group(&file, uid, gid)?;
+        }
+        file.write_all(content)?;
+        file.sync_all()?;
+        fs::rename(&tmp, dst).wrap_err_with(|| eyre!("rename to {} fail", dst.display()))
+    };
+
+    write_tmp().inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// atomically point `symlink` to `target`, replacing whatever it was
+pub fn replace_symlink(target: &Path, symlink: &Path) -> Result<()> {
+    let ((), tmp) = create_beside(symlink, |tmp| std::os::unix::fs::symlink(target, tmp))?;
+    fs::rename(&tmp, symlink)
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&tmp);
+        })
+        .wrap_err_with(|| eyre!("replace symlink {} error", symlink.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn never_follows_planted_symlink() {
+        let dir = std::env::temp_dir().join(format!("vaultix-atomic-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir(&dir).unwrap();
+        let victim = dir.join("victim");
+        fs::write(&victim, b"untouched").unwrap();
+        let dst = dir.join("tok");
+        std::os::unix::fs::symlink(&victim, dir.join(".tok.tmp")).unwrap();
+
+        write_file(&dst, b"secret", 0o400, None).unwrap();
+        assert_eq!(fs::read(&victim).unwrap(), b"untouched");
+        assert!(fs::symlink_metadata(&dst).unwrap().is_file());
+        assert_eq!(fs::read(&dst).unwrap(), b"secret");
+
+        replace_symlink(&victim, &dst).unwrap();
+        assert_eq!(fs::read_link(&dst).unwrap(), victim);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,286 @@
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    io::{self, Read, Write},
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+};
+
+use eyre::{Context, Result, eyre};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use super::{
+    atomic::{replace_symlink, write_file},
+    shred::{shred_dir_all, shred_file},
+};
+
+pub const MANIFEST_NAME: &str = ".vaultix-manifest.json";
+pub const HASH_KEY_NAME: &str = ".vaultix-hash-key";
+
+/// record of a single generation, stored inside the generation dir
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    /// destinations deployed outside of the generation dir
+    pub external: Vec<PathBuf>,
+    /// every deployed secret and template
+    #[serde(default)]
+    pub items: Vec<ManifestItem>,
+}
+
+/// deployed file, paths are as seen inside the deployed system
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestItem {
+    pub kind: String,
+    pub id: String,
+    pub path: PathBuf,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    /// hex of plaintext keyed blake3 hash, see [`HashKey`]
+    pub hash: String,
+    /// custom path linked to `path`, of a secret in symlink mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<ManifestLink>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestLink {
+    pub path: PathBuf,
+    /// through the decrypted dir rather than the generation dir
+    pub target: PathBuf,
+}
+
+impl Manifest {
+    pub fn item(&self, kind: &str, id: &str) -> Option<&ManifestItem> {
+        self.items.iter().find(|i| i.kind == kind && i.id == id)
+    }
+
+    pub fn read_from(gen_dir: &Path) -> Result<Self> {
+        match fs::read_to_string(gen_dir.join(MANIFEST_NAME)) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                debug!("no manifest found in {}", gen_dir.display());
+                Ok(Self::default())
+            }
+            Err(e) => Err(e).wrap_err_with(|| eyre!("read manifest of {gen_dir:?} fail")),
+            Ok(s) => serde_json::from_str(&s)
+                .wrap_err_with(|| eyre!("parse manifest of {gen_dir:?} fail")),
+        }
+    }
+
+    pub fn write_to(&self, gen_dir: &Path) -> Result<()> {
+        write_file(
+            &gen_dir.join(MANIFEST_NAME),
+            serde_json::to_string(self)?.as_bytes(),
+            0o400,
+            None,
+        )
+        .wrap_err_with(|| eyre!("write manifest in {gen_dir:?} fail"))
+    }
+}
+
+/**
+random key of content hashes in manifest, so the hash of a weak secret
+couldn't be brute forced.
+
+Lives root only in decrypted mount point, thus shares lifetime with the
+generations hashed with it.
+*/
+pub struct HashKey([u8; 32]);
+
+impl HashKey {
+    /// existing key under the mount point
+    pub fn read(mount_point: &Path) -> Result<Option<Self>> {
+        let path = mount_point.join(HASH_KEY_NAME);
+        match fs::read(&path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).wrap_err_with(|| eyre!("read {} fail", path.display())),
+            Ok(mut b) => {
+                let key = b.as_slice().try_into().map(Self);
+                b.zeroize();
+                key.map(Some)
+                    .map_err(|_| eyre!("invalid hash key {}", path.display()))
+            }
+        }
+    }
+
+    /// read the key or create one if not exist
+    pub fn init(mount_point: &Path) -> Result<Self> {
+        if let Some(k) = Self::read(mount_point)? {
+            return Ok(k);
+        }
+        let path = mount_point.join(HASH_KEY_NAME);
+        let mut key = Self([0; 32]);
+        fs::File::open("/dev/urandom")
+            .and_then(|mut f| f.read_exact(&mut key.0))
+            .wrap_err_with(|| eyre!("generate hash key fail"))?;
+        OpenOptions::new()
+            .create_new(true)
+            .write(true)
+            .mode(0o400)
+            .open(&path)
+            .and_then(|mut f| f.write_all(&key.0))
+            .wrap_err_with(|| eyre!("create {} fail", path.display()))?;
+        debug!("created hash key {}", path.display());
+        Ok(key)
+    }
+
+    pub fn hash(&self, content: &[u8]) -> String {
+        blake3::keyed_hash(&self.0, content).to_hex().to_string()
+    }
+}
+
+impl Drop for HashKey {
+    fn drop(&mut self) {
+        self.0.zeroize()
+    }
+}
+
+/// `p` of a system mounted at `root`, as seen from current one
+pub fn under_root(root: &Path, p: &Path) -> PathBuf {
+    root.join(p.strip_prefix("/").unwrap_or(p))
+}
+
+/// directory holding numbered generations, `<decrypted_mount_point>/<type>`
+pub struct Generations {
+    dir: PathBuf,
+    /// alternate root the dir lives in, links are made relative to it
+    root: Option<PathBuf>,
+}
+
+impl Generations {
+    pub fn new(dir: PathBuf, root: Option<PathBuf>) -> Self {
+        Self { dir, root }
+    }
+
+    pub fn path_of(&self, generation: usize) -> PathBuf {
+        self.dir.join(generation.to_string())
+    }
+
+    /// `p` as seen from inside the root
+    pub fn unrooted(&self, p: &Path) -> PathBuf {
+        match &self.root {
+            Some(r) => Path::new("/").join(p.strip_prefix(r).unwrap_or(p)),
+            None => p.to_path_buf(),
+        }
+    }
+
+    /// `p` recorded inside the root, as seen from current system
+    fn rooted(&self, p: &Path) -> PathBuf {
+        match &self.root {
+            Some(r) => under_root(r, p),
+            None => p.to_path_buf(),
+        }
+    }
+
+    /// ascending generation numbers, empty if the dir not created yet
+    pub fn list(&self) -> Result<Vec<usize>> {
+        let dir = match fs::read_dir(&self.dir) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            e => e.wrap_err_with(|| eyre!("reading {:?} fail", self.dir))?,
+        };
+        let mut ret = dir
+            .map(|en| {
+                en.wrap_err_with(|| eyre!("enter subdir of decrypted mount point fail"))
+                    .and_then(|d| {
+                        str::parse::<usize>(d.file_name().to_string_lossy().as_ref())
+                            .map_err(|e| eyre!("parse generation fail: {e}"))
+                    })
+            })
+            .collect::<Result<Vec<usize>>>()?;
+        ret.sort_unstable();
+        debug!("found generations {ret:?} in {:?}", self.dir);
+        Ok(ret)
+    }
+
+    /// generation the `symlink` currently points to, if it is one of ours
+    pub fn linked(&self, symlink: &Path) -> Option<usize> {
+        let target = fs::read_link(symlink).ok()?;
+        if target.parent()? != self.unrooted(&self.dir) {
+            return None;
+        }
+        str::parse::<usize>(target.file_name()?.to_str()?).ok()
+    }
+
+    /// atomically point `symlink` to the given generation
+    pub fn link(&self, generation: usize, symlink: &Path) -> Result<()> {
+        let target = self.unrooted(&self.path_of(generation));
+        info!(
+            "linking decrypted dir {} to {}",
+            target.display(),
+            symlink.display()
+        );
+        replace_symlink(&target, symlink)
+    }
+
+    /**
+    remove all generations except the newest `keep + 1` and the linked one.
+
+    Destinations outside generation dir which recorded in removed
+    generations but not in the retained ones are removed as well.
+    */
+    pub fn prune(&self, keep: usize, linked: Option<usize>) -> Result<()> {
+        let gens = self.list()?;
+        let removal = select_prunable(&gens, keep, linked);
+        if removal.is_empty() {
+            debug!("no generation needs to prune");
+            return Ok(());
+        }
+
+        let retained_external: HashSet<PathBuf> = gens
+            .iter()
+            .filter(|g| !removal.contains(g))
+            .map(|g| Manifest::read_from(&self.path_of(*g)).map(|m| m.external))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        for g in removal {
+            let gen_dir = self.path_of(g);
+            info!("pruning generation {}", gen_dir.display());
+
+            match Manifest::read_from(&gen_dir) {
+                Ok(m) => m
+                    .external
+                    .iter()
+                    .filter(|p| !retained_external.contains(*p))
+                    .try_for_each(|p| {
+                        let p = self.rooted(p);
+                        info!("removing {} belongs to pruned generation", p.display());
+                        shred_file(&p)
+                    })?,
+                Err(e) => warn!("{e}, skip removing its external files"),
+            }
+
+            shred_dir_all(&gen_dir)?;
+        }
+        Ok(())
+    }
+}
+
+fn select_prunable(gens: &[usize], keep: usize, linked: Option<usize>) -> Vec<usize> {
+    let mut sorted = gens.to_vec();
+    sorted.sort_unstable_by(|a, b| b.cmp(a));
+    sorted
+        .into_iter()
+        .skip(keep + 1)
+        .filter(|g| Some(*g) != linked)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prunable_generations() {
+        assert_eq!(select_prunable(&[0, 1, 2, 3], 1, Some(3)), vec![1, 0]);
+        assert_eq!(select_prunable(&[3, 0, 2, 1], 0, Some(3)), vec![2, 1, 0]);
+        assert_eq!(select_prunable(&[0, 1, 2, 3], 1, Some(0)), vec![1]);
+        assert!(select_prunable(&[0, 1], 1, None).is_empty());
+        assert!(select_prunable(&[], 0, None).is_empty());
+    }
+}
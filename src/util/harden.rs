@@ -0,0 +1,48 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use eyre::{Result, bail};
+use log::{debug, warn};
+
+static MLOCK_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// disable core dumps and ptrace attaching from unprivileged processes
+pub fn disable_core_dump() -> Result<()> {
+    let rlim = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::setrlimit(libc::RLIMIT_CORE, &rlim) } != 0 {
+        bail!(
+            "set RLIMIT_CORE failed: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    if unsafe { libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0) } != 0 {
+        bail!(
+            "set PR_SET_DUMPABLE failed: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+    debug!("core dump disabled");
+    Ok(())
+}
+
+/**
+keep pages holding `buf` out of swap.
+
+Pages are never unlocked since they may be shared with other
+locked buffers, the lock is released on process exit.
+*/
+pub fn mlock(buf: &Vec<u8>) {
+    if buf.capacity() == 0 {
+        return;
+    }
+    if unsafe { libc::mlock(buf.as_ptr().cast(), buf.capacity()) } != 0
+        && !MLOCK_WARNED.swap(true, Ordering::Relaxed)
+    {
+        warn!(
+            "mlock plaintext failed, it may be swapped to disk: {}",
+            std::io::Error::last_os_error()
+        );
+    }
+}
@@ -0,0 +1,28 @@
+use std::{ffi::CString, os::unix::ffi::OsStrExt, path::Path};
+
+use eyre::{Result, bail, eyre};
+
+// not exported by libc
+const RAMFS_MAGIC: i64 = 0x858458f6;
+
+/// filesystem magic number of the fs containing `path`
+pub fn fs_magic(path: &Path) -> Result<i64> {
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|_| eyre!("invalid path: {}", path.display()))?;
+    let mut st: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(c_path.as_ptr(), &mut st) } != 0 {
+        bail!(
+            "statfs {} failed: {}",
+            path.display(),
+            std::io::Error::last_os_error()
+        );
+    }
+    #[allow(clippy::unnecessary_cast)]
+    Ok(st.f_type as i64)
+}
+
+/// whether `path` is on ramfs or tmpfs
+#[allow(clippy::unnecessary_cast)]
+pub fn is_memory_backed(path: &Path) -> Result<bool> {
+    fs_magic(path).map(|m| m == RAMFS_MAGIC || m == libc::TMPFS_MAGIC as i64)
+}
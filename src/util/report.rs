@@ -0,0 +1,123 @@
+use std::{path::Path, path::PathBuf, str::FromStr, sync::Mutex, time::Instant};
+
+use serde::Serialize;
+
+/// format of the result document printed on stdout
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Output {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for Output {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            o => Err(format!(
+                "unknown output format `{o}`, expect `text` or `json`"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemError {
+    item: String,
+    path: Option<PathBuf>,
+    cause: String,
+}
+
+/// result of a single command execution, for `--output json`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    command: &'static str,
+    success: bool,
+    duration_ms: u128,
+    processed: usize,
+    skipped: usize,
+    written: Vec<PathBuf>,
+    errors: Vec<ItemError>,
+    error: Option<String>,
+    data: Option<serde_json::Value>,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+// collecting only happens in json mode
+static REPORT: Mutex<Option<Report>> = Mutex::new(None);
+
+fn with(f: impl FnOnce(&mut Report)) {
+    if let Some(r) = REPORT.lock().expect("report lock poisoned").as_mut() {
+        f(r)
+    }
+}
+
+/// start collecting result of `command`
+pub fn start(command: &'static str) {
+    *REPORT.lock().expect("report lock poisoned") = Some(Report {
+        command,
+        success: false,
+        duration_ms: 0,
+        processed: 0,
+        skipped: 0,
+        written: vec![],
+        errors: vec![],
+        error: None,
+        data: None,
+        started: Some(Instant::now()),
+    });
+}
+
+pub fn is_json() -> bool {
+    REPORT.lock().expect("report lock poisoned").is_some()
+}
+
+pub fn processed(n: usize) {
+    with(|r| r.processed += n)
+}
+
+pub fn skipped(n: usize) {
+    with(|r| r.skipped += n)
+}
+
+pub fn written(path: &Path) {
+    with(|r| {
+        r.processed += 1;
+        r.written.push(path.to_path_buf())
+    })
+}
+
+pub fn failed(item: impl Into<String>, path: Option<&Path>, cause: &eyre::Report) {
+    with(|r| {
+        r.processed += 1;
+        r.errors.push(ItemError {
+            item: item.into(),
+            path: path.map(Path::to_path_buf),
+            cause: format!("{cause:#}"),
+        })
+    })
+}
+
+/// command specific result, replacing what is printed in text mode
+pub fn data(value: impl Serialize) {
+    with(|r| r.data = serde_json::to_value(value).ok())
+}
+
+/// print the collected document, if collecting
+pub fn finish(res: &eyre::Result<()>) {
+    let Some(mut r) = REPORT.lock().expect("report lock poisoned").take() else {
+        return;
+    };
+    r.success = res.is_ok();
+    r.error = res.as_ref().err().map(|e| format!("{e:#}"));
+    r.duration_ms = r.started.map_or(0, |s| s.elapsed().as_millis());
+    match serde_json::to_string(&r) {
+        Ok(s) => println!("{s}"),
+        Err(e) => log::error!("serialize report fail: {e}"),
+    }
+}
@@ -1,10 +1,8 @@
-use std::fs::{OpenOptions, Permissions};
-use std::io::Write;
-use std::os::unix::fs::PermissionsExt;
 use std::path::PathBuf;
-use std::{io::Read, iter, marker::PhantomData};
+use std::{fmt, io::Read, iter, marker::PhantomData};
 
 use age::{Identity, Recipient};
+use zeroize::{Zeroize, Zeroizing};
 #[derive(Debug, Clone)]
 pub struct AgeEnc;
 #[derive(Debug, Clone)]
@@ -12,26 +10,82 @@ pub struct HostEnc;
 #[derive(Debug, Clone)]
 pub struct Plain;
 
-#[derive(Debug, Clone)]
-pub struct SecBuf<T> {
+/// content type of `SecBuf`
+pub trait BufKind {
+    /// whether the content should be kept out of swap
+    const SENSITIVE: bool;
+}
+
+macro_rules! impl_buf_kind {
+    ($($type:ty => $sensitive:expr),+) => {
+        $(
+            impl BufKind for $type {
+                const SENSITIVE: bool = $sensitive;
+            }
+        )+
+    };
+}
+
+impl_buf_kind!(AgeEnc => false, HostEnc => false, Plain => true);
+
+/// byte buffer tagged with its content type, wiped on drop
+pub struct SecBuf<T: BufKind> {
     buf: Vec<u8>,
     _marker: PhantomData<T>,
 }
 
-impl<T> SecBuf<T> {
+impl<T: BufKind> Drop for SecBuf<T> {
+    fn drop(&mut self) {
+        self.buf.zeroize();
+    }
+}
+
+impl<T: BufKind> fmt::Debug for SecBuf<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecBuf")
+            .field("len", &self.buf.len())
+            .finish()
+    }
+}
+
+// plaintext is intentionally not `Clone`
+macro_rules! impl_clone_cipher {
+    ($($type:ty),+) => {
+        $(
+            impl Clone for SecBuf<$type> {
+                fn clone(&self) -> Self {
+                    Self::new(self.buf.clone())
+                }
+            }
+        )+
+    };
+}
+
+impl_clone_cipher!(AgeEnc, HostEnc);
+
+impl<T: BufKind> SecBuf<T> {
     pub fn new(i: Vec<u8>) -> Self {
+        if T::SENSITIVE {
+            harden::mlock(&i);
+        }
         SecBuf {
             buf: i,
             _marker: PhantomData,
         }
     }
-    pub fn inner(self) -> Vec<u8> {
-        self.buf
+    /// take the content out, caller is responsible for wiping it
+    pub fn inner(mut self) -> Vec<u8> {
+        std::mem::take(&mut self.buf)
     }
     pub fn inner_ref(&self) -> &Vec<u8> {
         self.buf.as_ref()
     }
 
+    /// write to a temporary file next to `dst` with given mode, then rename over `dst`
+    pub fn write_atomic(&self, dst: &std::path::Path, mode: u32) -> Result<()> {
+        atomic::write_file(dst, self.buf_ref(), mode, None)
+    }
+
     pub fn hash_with(&self, host_ssh_recip: &str) -> blake3::Hash {
         let mut hasher = blake3::Hasher::new();
         hasher.update(&self.buf);
@@ -41,7 +95,7 @@ impl<T> SecBuf<T> {
 }
 
 use eyre::Result;
-impl<T> SecBuf<T> {
+impl<T: BufKind> SecBuf<T> {
     pub fn buf_ref(&self) -> &Vec<u8> {
         self.buf.as_ref()
     }
@@ -58,7 +112,9 @@ macro_rules! impl_decryptable {
                 let buffer = self.buf_ref();
                 let decryptor = age::Decryptor::new(&buffer[..])?;
 
-                let mut dec_content = vec![];
+                // plaintext is shorter than ciphertext, reserve to avoid
+                // reallocation leaving copies in freed memory
+                let mut dec_content = Vec::with_capacity(buffer.len());
                 let mut reader = decryptor.decrypt(iter::once(ident))?;
                 let res = reader.read_to_end(&mut dec_content);
                 if let Ok(b) = res {
@@ -73,12 +129,9 @@ macro_rules! impl_decryptable {
 impl_decryptable!(SecBuf<HostEnc>);
 impl_decryptable!(SecBuf<AgeEnc>);
 
-impl<T> From<Vec<u8>> for SecBuf<T> {
+impl<T: BufKind> From<Vec<u8>> for SecBuf<T> {
     fn from(value: Vec<u8>) -> Self {
-        Self {
-            buf: value,
-            _marker: PhantomData,
-        }
+        Self::new(value)
     }
 }
 
@@ -94,17 +147,17 @@ impl SecBuf<AgeEnc> {
 }
 
 use eyre::eyre;
-use log::{debug, trace};
+use log::debug;
 
 use crate::parser::extract_all_hashes;
 use crate::profile::InsertSet;
 
-use super::set_owner_group;
+use super::{atomic, harden};
 
 impl SecBuf<Plain> {
     /// encrypt with host pub key, ssh key
     pub fn encrypt<'a>(
-        self,
+        &self,
         recips: impl Iterator<Item = &'a (dyn Recipient + Send)>,
     ) -> Result<SecBuf<HostEnc>> {
         let recips = recips.map(|r| r as &dyn Recipient);
@@ -122,48 +175,26 @@ impl SecBuf<Plain> {
         Ok(SecBuf::new(enc_content))
     }
 
-    pub fn deploy_to_fs(
-        &self,
-        item: impl crate::profile::DeployFactor,
-        dst: PathBuf,
-    ) -> Result<()> {
-        let mut the_file = {
-            let mode = crate::parser::parse_permissions_str(item.mode())
-                .map_err(|e| eyre!("parse octal permission err: {}", e))?;
-            let permissions = Permissions::from_mode(mode);
-            trace!("apply file permission: {permissions:?}");
-
-            let file = OpenOptions::new()
-                .create(true)
-                .truncate(true)
-                .write(true)
-                .open(dst)?;
-
-            file.set_permissions(permissions)?;
-
-            set_owner_group::set_owner_and_group(&file, item.owner(), item.group())?;
-
-            file
-        };
-        the_file.write_all(self.buf_ref())?;
-        Ok(())
+    /// write to a temporary file next to `dst` then rename it over `dst`
+    pub fn deploy_to_fs(&self, mode: u32, owner: (u32, u32), dst: PathBuf) -> Result<()> {
+        atomic::write_file(&dst, self.buf_ref(), mode, Some(owner))
     }
 
     pub fn insert(&mut self, ins_set: &InsertSet, clean_after_replace_complete: bool) {
         let ins_map = &ins_set.0;
         let mut hash_extract_res = vec![];
 
-        let self_string = String::from_utf8(self.inner_ref().clone()).expect("must");
+        let self_str = std::str::from_utf8(self.buf_ref()).expect("must");
 
-        extract_all_hashes(self_string.as_str(), &mut hash_extract_res);
+        extract_all_hashes(self_str, &mut hash_extract_res);
 
-        log::trace!("{:?}", &hash_extract_res);
+        log::trace!("{:?}", hash_extract_res);
 
         let mut ins_map: Vec<_> = ins_map.iter().collect();
 
         ins_map.sort_by_key(|(_, v)| v.order);
 
-        let mut new_string = self_string.clone();
+        let mut new_string = Zeroizing::new(self_str.to_owned());
 
         let brace_the_str = |s: &str| -> String { format!("{{{{ {s} }}}}") };
 
@@ -172,7 +203,7 @@ impl SecBuf<Plain> {
                 log::debug!("inserting content corresponding to placeholder: {k}");
                 let braced_hash_str = brace_the_str(k);
                 let string_after_this_replace =
-                    new_string.replace(braced_hash_str.as_str(), &v.content);
+                    Zeroizing::new(new_string.replace(braced_hash_str.as_str(), &v.content));
                 new_string = string_after_this_replace;
                 hash_extract_res.retain(|&x| x != k.as_str());
             } else {
@@ -184,12 +215,13 @@ impl SecBuf<Plain> {
         if clean_after_replace_complete {
             hash_extract_res.iter().for_each(|i| {
                 let braced_hash_str = brace_the_str(i);
-                let string_after_clean =
-                    new_string.replace(braced_hash_str.as_str(), String::default().as_str());
+                let string_after_clean = Zeroizing::new(
+                    new_string.replace(braced_hash_str.as_str(), String::default().as_str()),
+                );
                 new_string = string_after_clean;
             });
         }
-        *self = SecBuf::<Plain>::new(new_string.into_bytes())
+        *self = SecBuf::<Plain>::new(std::mem::take(&mut *new_string).into_bytes())
     }
 }
 
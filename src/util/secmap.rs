@@ -18,7 +18,7 @@ use eyre::{Result, eyre};
 use log::debug;
 use std::marker::PhantomData;
 
-use super::secbuf::{Decryptable, HostEnc, SecBuf};
+use super::secbuf::{BufKind, Decryptable, HostEnc, Plain, SecBuf};
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct SecPath<P: AsRef<Path>, T> {
@@ -94,10 +94,10 @@ impl<'a> RencInstance<'a> {
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct RencCtx<'a, B>(DashMap<&'a Secret, SecBuf<B>>);
+#[derive(Debug)]
+pub struct RencCtx<'a, B: BufKind>(DashMap<&'a Secret, SecBuf<B>>);
 
-impl<'a, B> RencCtx<'a, B> {
+impl<'a, B: BufKind> RencCtx<'a, B> {
     pub fn inner_ref(&self) -> &DashMap<&'a Secret, SecBuf<B>> {
         &self.0
     }
@@ -241,7 +241,7 @@ impl<'a> RencData<'a, InStore> {
     pub fn bake_decrypted(
         self,
         idents: Vec<Box<dyn Identity>>,
-    ) -> Result<HashMap<&'a Secret, Vec<u8>>> {
+    ) -> Result<HashMap<&'a Secret, SecBuf<Plain>>> {
         let mut verified_key_idx = None;
         self.inner()
             .into_iter()
@@ -264,9 +264,9 @@ impl<'a> RencData<'a, InStore> {
                         }
                         Err(eyre!("no key is able to decrypt the secret"))
                     })
-                    .map(|i| (k.0, i.inner()))
+                    .map(|i| (k.0, i))
             })
-            .try_collect::<HashMap<&'a Secret, Vec<u8>>>()
+            .try_collect::<HashMap<&'a Secret, SecBuf<Plain>>>()
     }
 }
 
@@ -306,10 +306,12 @@ impl<'a> RencData<'a, InRepo> {
         })
     }
 
-    /// retain non exist path
-    pub fn retain_noexist(&mut self) {
+    /// retain non exist path, return the count of skipped
+    pub fn retain_noexist(&mut self) -> usize {
         // TODO: check if all cache added to git?
-        self.inner_ref_mut().retain(|_, v| !v.path.exists())
+        let before = self.inner_ref().len();
+        self.inner_ref_mut().retain(|_, v| !v.path.exists());
+        before - self.inner_ref().len()
     }
 
     pub fn build_instance(&self) -> RencInstance<'a> {
@@ -317,13 +319,9 @@ impl<'a> RencData<'a, InRepo> {
             self.inner_ref()
                 .iter()
                 .fold(DashMap::new(), |acc, ((x, y), z)| {
-                    if !acc.contains_key(y) {
-                        acc.insert(y.clone(), vec![(*x, z.clone())]);
-                    }
-                    acc._entry(y.clone()).and_modify(|i| {
-                        i.push((x, z.clone()));
-                    });
-
+                    acc._entry(y.clone())
+                        .and_modify(|i| i.push((x, z.clone())))
+                        .or_insert_with(|| vec![(*x, z.clone())]);
                     acc
                 }),
         )
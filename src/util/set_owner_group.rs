@@ -1,20 +1,87 @@
-use eyre::{Result, eyre};
+use eyre::{Context, Result, eyre};
 use libc::{fchown, getgrnam, getpwnam};
 use log::warn;
-use std::{ffi::CString, fs::File, os::fd::AsRawFd};
+use std::{collections::HashMap, ffi::CString, fs, fs::File, os::fd::AsRawFd, path::Path};
 
-pub fn set_owner_and_group(file: &File, owner: &str, group: &str) -> Result<()> {
+/// database owners and groups are resolved from
+pub enum Accounts {
+    /// nss of the running system
+    Host,
+    /// `etc/passwd` and `etc/group` of an alternate root
+    Files {
+        users: HashMap<String, u32>,
+        groups: HashMap<String, u32>,
+    },
+}
+
+impl Accounts {
+    pub fn of_root(root: Option<&Path>) -> Result<Self> {
+        let Some(root) = root else {
+            return Ok(Self::Host);
+        };
+        let read = |name: &str| {
+            let p = root.join("etc").join(name);
+            fs::read_to_string(&p)
+                .map(|c| parse_id_db(&c))
+                .wrap_err_with(|| eyre!("read {} fail", p.display()))
+        };
+        Ok(Self::Files {
+            users: read("passwd")?,
+            groups: read("group")?,
+        })
+    }
+
+    fn uid(&self, owner: &str) -> Result<u32> {
+        match self {
+            Self::Host => get_uid_from_username(owner),
+            Self::Files { users, .. } => users
+                .get(owner)
+                .copied()
+                .ok_or_else(|| eyre!("User '{}' not found", owner)),
+        }
+    }
+
+    fn gid(&self, group: &str) -> Result<u32> {
+        match self {
+            Self::Host => get_gid_from_groupname(group),
+            Self::Files { groups, .. } => groups
+                .get(group)
+                .copied()
+                .ok_or_else(|| eyre!("Group '{}' not found", group)),
+        }
+    }
+
+    /// uid and gid of the given names, fallback to 0 if not found
+    pub fn resolve(&self, owner: &str, group: &str) -> (u32, u32) {
+        let user_uid = self.uid(owner).unwrap_or_else(|_| {
+            warn!("get uid of {owner} failed, fallback to uid 0");
+            0
+        });
+        let group_gid = self.gid(group).unwrap_or_else(|_| {
+            warn!("get gid of {group} failed, fallback to uid 0");
+            0
+        });
+        (user_uid, group_gid)
+    }
+}
+
+/// name to id of passwd(5) or group(5) formatted content
+fn parse_id_db(content: &str) -> HashMap<String, u32> {
+    content
+        .lines()
+        .filter_map(|l| {
+            let mut fields = l.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((name.to_string(), id))
+        })
+        .collect()
+}
+
+pub fn set_owner_and_group(file: &File, uid: u32, gid: u32) -> Result<()> {
     let fd = file.as_raw_fd();
 
-    let user_uid = get_uid_from_username(owner).or_else(|_| {
-        warn!("get uid of {owner} failed, fallback to uid 0");
-        eyre::Ok(0)
-    })?;
-    let group_gid = get_gid_from_groupname(group).or_else(|_| {
-        warn!("get gid of {group} failed, fallback to uid 0");
-        eyre::Ok(0)
-    })?;
-    let result = unsafe { fchown(fd, user_uid, group_gid) };
+    let result = unsafe { fchown(fd, uid, gid) };
 
     if result == -1 {
         eyre::bail!("set permission failed");
@@ -46,3 +113,22 @@ fn get_gid_from_groupname(groupname: &str) -> Result<u32> {
         Ok((*gr).gr_gid)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn id_db() {
+        let db = parse_id_db(
+            "root:x:0:0:System administrator:/root:/bin/sh\n\
+             nginx:x:60:60::/var/empty:/run/current-system/sw/bin/nologin\n\
+             # comment\n\
+             broken:x\n",
+        );
+        assert_eq!(db.get("root"), Some(&0));
+        assert_eq!(db.get("nginx"), Some(&60));
+        assert_eq!(db.len(), 2);
+        assert_eq!(parse_id_db("wheel:x:1:alice,bob").get("wheel"), Some(&1));
+    }
+}
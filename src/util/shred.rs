@@ -0,0 +1,63 @@
+use std::{
+    fs::{self, File, OpenOptions, Permissions},
+    io::{self, Write},
+    os::unix::fs::PermissionsExt,
+    path::Path,
+};
+
+use eyre::{Context, Result, eyre};
+use log::debug;
+
+const ZERO_CHUNK: [u8; 4096] = [0u8; 4096];
+
+/// overwrite an opened regular file with zeros
+pub fn zero_out(f: &mut File) -> io::Result<()> {
+    let mut remain = f.metadata()?.len();
+    while remain > 0 {
+        let n = remain.min(ZERO_CHUNK.len() as u64) as usize;
+        f.write_all(&ZERO_CHUNK[..n])?;
+        remain -= n as u64;
+    }
+    f.sync_all()
+}
+
+/// overwrite a regular file with zeros, then unlink it.
+/// symlinks are unlinked without touching their target.
+pub fn shred_file(path: &Path) -> Result<()> {
+    let meta = match fs::symlink_metadata(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        e @ Err(_) => e.wrap_err_with(|| eyre!("stat {} fail", path.display()))?,
+        Ok(o) => o,
+    };
+
+    if meta.file_type().is_file() {
+        // read only one is writable by root anyway, and by owner after this
+        let mode = meta.permissions().mode();
+        if mode & 0o200 == 0 {
+            fs::set_permissions(path, Permissions::from_mode(mode | 0o200))
+                .wrap_err_with(|| eyre!("make {} writable fail", path.display()))?;
+        }
+        OpenOptions::new()
+            .write(true)
+            .open(path)
+            .wrap_err_with(|| eyre!("open {} for overwriting fail", path.display()))
+            .and_then(|mut f| zero_out(&mut f).map_err(Into::into))?;
+    }
+
+    debug!("unlinking {}", path.display());
+    fs::remove_file(path).wrap_err_with(|| eyre!("remove {} fail", path.display()))
+}
+
+/// shred every file below `dir` and remove the directory tree
+pub fn shred_dir_all(dir: &Path) -> Result<()> {
+    for entry in fs::read_dir(dir).wrap_err_with(|| eyre!("read {} fail", dir.display()))? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            shred_dir_all(&path)?;
+        } else {
+            shred_file(&path)?;
+        }
+    }
+    fs::remove_dir(dir).wrap_err_with(|| eyre!("remove dir {} fail", dir.display()))
+}